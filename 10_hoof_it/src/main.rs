@@ -4,14 +4,20 @@ use std::{
     io::{BufRead, BufReader, Error},
 };
 
+use stats::Stats;
+
 const DIRECTIONS: &[(isize, isize)] = &[(-1, 0), (1, 0), (0, -1), (0, 1)];
 
 fn get_rating(
     map: &[Vec<u32>],
     cache: &mut HashMap<(usize, usize), usize>,
     position: (usize, usize),
+    stats: &mut Stats,
 ) -> usize {
+    stats.incr("Rating calls");
+
     if let Some(rating) = cache.get(&position) {
+        stats.incr("Cache hits");
         return *rating;
     }
 
@@ -35,7 +41,7 @@ fn get_rating(
                 return None;
             }
 
-            Some(get_rating(map, cache, new_pos))
+            Some(get_rating(map, cache, new_pos, stats))
         })
         .sum();
 
@@ -59,6 +65,7 @@ fn main() -> Result<(), Error> {
         .collect::<Vec<_>>();
 
     let mut cache: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut stats = Stats::new(&["Rating calls", "Cache hits"]);
 
     let score_sum = map
         .iter()
@@ -67,12 +74,14 @@ fn main() -> Result<(), Error> {
             line.iter()
                 .enumerate()
                 .filter(|(_, height)| **height == 0)
-                .map(|(col_idx, _)| get_rating(&map, &mut cache, (line_idx, col_idx)))
+                .map(|(col_idx, _)| get_rating(&map, &mut cache, (line_idx, col_idx), &mut stats))
                 .sum::<usize>()
         })
         .sum::<usize>();
 
     println!("Score sum is {score_sum}");
 
+    stats.print_if_requested();
+
     Ok(())
 }
@@ -1,13 +1,15 @@
 use std::{
     collections::HashMap,
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{stdin, stdout, BufRead, BufReader, Error, Write},
 };
 
+use stats::Stats;
+
 fn split_if_even(num: usize) -> Option<(usize, usize)> {
     let num_str = num.to_string();
 
-    if num_str.len() % 2 != 0 {
+    if !num_str.len().is_multiple_of(2) {
         return None;
     }
 
@@ -21,21 +23,53 @@ fn split_if_even(num: usize) -> Option<(usize, usize)> {
     Some((*left, *right))
 }
 
-fn blink(stone: usize, blinks: usize, cache: &mut HashMap<(usize, usize), usize>) -> usize {
+/// how often the stones blink, 75 unless `config.txt` or `--blinks=N` says otherwise
+struct Config {
+    blinks: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { blinks: 75 }
+    }
+}
+
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "blinks" => self.blinks = value.parse().map_err(invalid)?,
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+}
+
+fn blink(
+    stone: usize,
+    blinks: usize,
+    cache: &mut HashMap<(usize, usize), usize>,
+    stats: &mut Stats,
+) -> usize {
     let Some(blinks) = blinks.checked_sub(1) else {
         return 1;
     };
 
     if let Some(res) = cache.get(&(stone, blinks)) {
+        stats.incr("Cache hits");
         return *res;
     }
 
+    stats.incr("Cache misses");
+
     let result = if stone == 0 {
-        blink(1, blinks, cache)
+        blink(1, blinks, cache, stats)
     } else if let Some((left, right)) = split_if_even(stone) {
-        blink(left, blinks, cache) + blink(right, blinks, cache)
+        blink(left, blinks, cache, stats) + blink(right, blinks, cache, stats)
     } else {
-        blink(stone * 2024, blinks, cache)
+        blink(stone * 2024, blinks, cache, stats)
     };
 
     cache.insert((stone, blinks), result);
@@ -43,7 +77,127 @@ fn blink(stone: usize, blinks: usize, cache: &mut HashMap<(usize, usize), usize>
     result
 }
 
+fn count_stones(
+    stones: &HashMap<usize, usize>,
+    blinks: usize,
+    cache: &mut HashMap<(usize, usize), usize>,
+    stats: &mut Stats,
+) -> usize {
+    stones
+        .iter()
+        .map(|(stone, amount)| blink(*stone, blinks, cache, stats) * amount)
+        .sum()
+}
+
+/// applies a single blink to every stone, keeping track of how often each number occurs
+fn blink_once(stones: &HashMap<usize, usize>) -> HashMap<usize, usize> {
+    let mut new_stones: HashMap<usize, usize> = HashMap::new();
+
+    for (stone, amount) in stones {
+        if *stone == 0 {
+            *new_stones.entry(1).or_default() += amount;
+        } else if let Some((left, right)) = split_if_even(*stone) {
+            *new_stones.entry(left).or_default() += amount;
+            *new_stones.entry(right).or_default() += amount;
+        } else {
+            *new_stones.entry(stone * 2024).or_default() += amount;
+        }
+    }
+
+    new_stones
+}
+
+fn count_occurences(stones: &[usize]) -> HashMap<usize, usize> {
+    let mut occurences: HashMap<usize, usize> = HashMap::new();
+
+    for stone in stones {
+        *occurences.entry(*stone).or_default() += 1;
+    }
+
+    occurences
+}
+
+fn repl(stones: &[usize], mut config: Config) -> Result<(), Error> {
+    let initial_stones = count_occurences(stones);
+    let mut current_stones = initial_stones.clone();
+    let mut blinks_done = 0;
+
+    let mut cache: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut stats = Stats::new(&["Cache hits", "Cache misses"]);
+
+    let mut lines = stdin().lock().lines();
+
+    loop {
+        print!("> ");
+        stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            [] => {}
+            ["part1"] => {
+                let count = count_stones(&initial_stones, 25, &mut cache, &mut stats);
+                println!("Stone count after 25 blinks: {count}");
+            }
+            ["part2"] => {
+                let count = count_stones(&initial_stones, 75, &mut cache, &mut stats);
+                println!("Stone count after 75 blinks: {count}");
+            }
+            ["count"] => {
+                let count = count_stones(&current_stones, config.blinks, &mut cache, &mut stats);
+                println!("Stone count after {} more blinks: {count}", config.blinks);
+            }
+            ["show"] => {
+                let mut stones = current_stones.iter().collect::<Vec<_>>();
+                stones.sort();
+
+                println!("After {blinks_done} blinks:");
+                for (stone, amount) in stones {
+                    println!("{stone} x{amount}");
+                }
+            }
+            ["step", amount] => {
+                let Ok(amount) = amount.parse::<usize>() else {
+                    println!("Invalid step amount {amount}");
+                    continue;
+                };
+
+                for _ in 0..amount {
+                    current_stones = blink_once(&current_stones);
+                }
+                blinks_done += amount;
+
+                let count: usize = current_stones.values().sum();
+                println!("After {blinks_done} blinks there are {count} stones");
+            }
+            ["reset"] => {
+                current_stones = initial_stones.clone();
+                blinks_done = 0;
+            }
+            ["set", key, value] => {
+                if let Err(err) = config.set(key, value) {
+                    println!("{err}");
+                }
+            }
+            ["stats"] => stats.print(),
+            ["help"] => {
+                println!("Commands: part1, part2, count, show, step <n>, reset, set blinks <n>, stats, quit");
+            }
+            ["quit" | "exit"] => break,
+            _ => println!("Unknown command {line}, try help"),
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
+    let config = config::load(Config::set)?;
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
 
@@ -59,14 +213,25 @@ fn main() -> Result<(), Error> {
         .map(|stone| stone.parse::<usize>().expect("valid number"))
         .collect::<Vec<_>>();
 
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    if args.get(1).is_some_and(|arg| arg == "repl") {
+        return repl(&stones, config);
+    }
+
     let mut cache: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut stats = Stats::new(&["Cache hits", "Cache misses"]);
 
     let stone_count: usize = stones
         .iter()
-        .map(|stone| blink(*stone, 75, &mut cache))
+        .map(|stone| blink(*stone, config.blinks, &mut cache, &mut stats))
         .sum();
 
     println!("Stone count: {stone_count}");
 
+    stats.print_if_requested();
+
     Ok(())
 }
@@ -1,28 +1,61 @@
-use core::panic;
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
 };
 
 use num::Integer;
 
-fn parse_button_value(value: &str) -> isize {
-    let (_, value) = value.split_once("+").expect("+ is present");
+fn parse_value(value: &str, separator: char) -> Result<isize, String> {
+    let (_, number) = value
+        .split_once(separator)
+        .ok_or_else(|| format!("expected '{separator}' in '{value}'"))?;
 
-    value.parse().expect("valid isize")
+    number
+        .parse()
+        .map_err(|_| format!("invalid number '{number}' in '{value}'"))
 }
 
-fn parse_target_value(value: &str) -> isize {
-    let (_, value) = value.split_once("=").expect("= is present");
+fn parse_target(line: &str) -> Result<(isize, isize), String> {
+    let (_, rest) = line
+        .split_once(": ")
+        .ok_or_else(|| format!("expected ': ' in '{line}'"))?;
+    let (x, y) = rest
+        .split_once(", ")
+        .ok_or_else(|| format!("expected ', ' in '{line}'"))?;
 
-    value.parse().expect("valid isize")
+    Ok((parse_value(x, '=')?, parse_value(y, '=')?))
 }
 
-fn parse_target(line: &str) -> (isize, isize) {
-    let (_, rest) = line.split_once(": ").expect("colon present");
-    let (x, y) = rest.split_once(", ").expect("comma present");
+/// token costs of the buttons and the offset added to every prize in part 2
+struct Config {
+    cost_a: isize,
+    cost_b: isize,
+    prize_offset: isize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            cost_a: 3,
+            cost_b: 1,
+            prize_offset: 10000000000000,
+        }
+    }
+}
 
-    (parse_target_value(x), parse_target_value(y))
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "cost_a" => self.cost_a = value.parse().map_err(invalid)?,
+            "cost_b" => self.cost_b = value.parse().map_err(invalid)?,
+            "prize_offset" => self.prize_offset = value.parse().map_err(invalid)?,
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -32,24 +65,30 @@ struct Button {
     cost: isize,
 }
 
-impl From<&String> for Button {
-    fn from(value: &String) -> Self {
-        let (_, rest) = value
-            .split_once("Button ")
-            .expect("Button starts with Button");
-        let (button_type, rest) = rest.split_once(": ").expect("colon is present");
+impl Button {
+    fn parse(value: &str, config: &Config) -> Result<Self, String> {
+        let rest = value
+            .strip_prefix("Button ")
+            .ok_or_else(|| format!("expected 'Button ' at the start of '{value}'"))?;
+        let (button_type, rest) = rest
+            .split_once(": ")
+            .ok_or_else(|| format!("expected ': ' in '{value}'"))?;
 
         let cost = match button_type {
-            "A" => 3,
-            "B" => 1,
-            c => panic!("Invalid button type {c}"),
+            "A" => config.cost_a,
+            "B" => config.cost_b,
+            c => return Err(format!("invalid button type '{c}'")),
         };
 
-        let (x, y) = rest.split_once(", ").expect("comma present");
-        let x = parse_button_value(x);
-        let y = parse_button_value(y);
+        let (x, y) = rest
+            .split_once(", ")
+            .ok_or_else(|| format!("expected ', ' in '{value}'"))?;
 
-        Button { x, y, cost }
+        Ok(Button {
+            x: parse_value(x, '+')?,
+            y: parse_value(y, '+')?,
+            cost,
+        })
     }
 }
 
@@ -111,7 +150,67 @@ impl Puzzle {
     }
 }
 
+/// reports machines that aren't three well-formed lines or whose buttons are collinear
+fn validate(lines: &[String], config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (machine_idx, machine) in lines.split(|line| line.is_empty()).enumerate() {
+        let machine_nr = machine_idx + 1;
+
+        let [button_a, button_b, target] = machine else {
+            problems.push(format!(
+                "Machine {machine_nr}: expected 3 lines, found {}",
+                machine.len()
+            ));
+            continue;
+        };
+
+        let mut is_well_formed = true;
+        for (line, prefix) in [
+            (button_a, "Button A: X+"),
+            (button_b, "Button B: X+"),
+            (target, "Prize: X="),
+        ] {
+            if !line.starts_with(prefix) {
+                problems.push(format!(
+                    "Machine {machine_nr}: expected line starting with '{prefix}', found '{line}'"
+                ));
+                is_well_formed = false;
+            }
+        }
+
+        if !is_well_formed {
+            continue;
+        }
+
+        let parsed = Button::parse(button_a, config).and_then(|button_a| {
+            let button_b = Button::parse(button_b, config)?;
+            parse_target(target)?;
+
+            Ok((button_a, button_b))
+        });
+
+        let (button_a, button_b) = match parsed {
+            Ok(buttons) => buttons,
+            Err(err) => {
+                problems.push(format!("Machine {machine_nr}: {err}"));
+                continue;
+            }
+        };
+
+        if get_determinant(button_a.x, button_a.y, button_b.x, button_b.y) == 0 {
+            problems.push(format!(
+                "Machine {machine_nr}: buttons A and B are collinear (determinant is 0)"
+            ));
+        }
+    }
+
+    problems
+}
+
 fn main() -> Result<(), Error> {
+    let config = config::load(Config::set)?;
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
 
@@ -120,24 +219,40 @@ fn main() -> Result<(), Error> {
         .map(|line| line.expect("valid line"))
         .collect::<Vec<_>>();
 
+    let problems = validate(&lines, &config);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "machines are unsolvable",
+        ));
+    }
+
     let puzzles = lines
         .split(|line| line.is_empty())
         .map(|lines| {
             let mut lines = lines.iter();
 
-            let button_a: Button = lines.next().expect("button a").into();
-            let button_b: Button = lines.next().expect("button b").into();
-            let target = parse_target(lines.next().expect("target"));
+            let button_a = Button::parse(lines.next().expect("button a"), &config)?;
+            let button_b = Button::parse(lines.next().expect("button b"), &config)?;
+            let target = parse_target(lines.next().expect("target"))?;
 
-            let target = (target.0 + 10000000000000, target.1 + 10000000000000);
+            let target = (
+                target.0 + config.prize_offset,
+                target.1 + config.prize_offset,
+            );
 
-            Puzzle {
+            Ok(Puzzle {
                 button_a,
                 button_b,
                 target,
-            }
+            })
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
 
     let costs: isize = puzzles
         .iter()
@@ -1,11 +1,61 @@
 use std::{
+    collections::HashSet,
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{stdin, stdout, BufRead, BufReader, Error, ErrorKind, Write},
+    path::Path,
 };
 
-const MAX_X: isize = 100;
-const MAX_Y: isize = 102;
-const WALK_AMOUNT: usize = 100;
+/// size of the bathroom and how long the robots move, the example uses an 11 by 7 room
+#[derive(Clone)]
+struct Config {
+    width: isize,
+    height: isize,
+    seconds: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 101,
+            height: 103,
+            seconds: 100,
+        }
+    }
+}
+
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "width" | "height" => {
+                let size = value
+                    .parse()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| format!("{key} has to be at least 1, found {value}"))?;
+
+                if key == "width" {
+                    self.width = size;
+                } else {
+                    self.height = size;
+                }
+            }
+            "seconds" => self.seconds = value.parse().map_err(invalid)?,
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+
+    fn max_x(&self) -> isize {
+        self.width - 1
+    }
+
+    fn max_y(&self) -> isize {
+        self.height - 1
+    }
+}
 
 fn parse_xy(value: &str) -> (isize, isize) {
     let (_, xy) = value.split_once('=').expect("xy seperated by =");
@@ -16,14 +66,9 @@ fn parse_xy(value: &str) -> (isize, isize) {
     (x, y)
 }
 
+/// wraps around as often as needed, velocities can be larger than the room
 fn wrap_in_bounding_box(value: isize, max: isize) -> isize {
-    if value < 0 {
-        max + value + 1
-    } else if value > max {
-        value - max - 1
-    } else {
-        value
-    }
+    value.rem_euclid(max + 1)
 }
 
 struct Robot {
@@ -32,22 +77,22 @@ struct Robot {
 }
 
 impl Robot {
-    fn simulate(&self) -> (isize, isize) {
+    fn simulate(&self, config: &Config) -> (isize, isize) {
         let mut pos = self.starting_pos;
 
-        for _ in 0..WALK_AMOUNT {
-            pos = self.walk(pos);
+        for _ in 0..config.seconds {
+            pos = self.walk(pos, config);
         }
 
         pos
     }
 
-    fn walk(&self, pos: (isize, isize)) -> (isize, isize) {
+    fn walk(&self, pos: (isize, isize), config: &Config) -> (isize, isize) {
         let new_pos = (pos.0 + self.velocity.0, pos.1 + self.velocity.1);
 
         (
-            wrap_in_bounding_box(new_pos.0, MAX_X),
-            wrap_in_bounding_box(new_pos.1, MAX_Y),
+            wrap_in_bounding_box(new_pos.0, config.max_x()),
+            wrap_in_bounding_box(new_pos.1, config.max_y()),
         )
     }
 }
@@ -66,22 +111,13 @@ impl From<String> for Robot {
     }
 }
 
-fn main() -> Result<(), Error> {
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-
-    let robots = reader
-        .lines()
-        .map(|line| line.expect("valid line"))
-        .map(|line| line.into())
-        .collect::<Vec<Robot>>();
-
-    let x_middle = MAX_X / 2;
-    let y_middle = MAX_Y / 2;
+fn get_safety_factor(positions: &[(isize, isize)], config: &Config) -> usize {
+    let x_middle = config.max_x() / 2;
+    let y_middle = config.max_y() / 2;
 
     let mut quadrants = [0; 4];
 
-    robots.iter().map(|robot| robot.simulate()).for_each(|pos| {
+    positions.iter().for_each(|pos| {
         if pos.0 == x_middle || pos.1 == y_middle {
             return;
         }
@@ -99,9 +135,266 @@ fn main() -> Result<(), Error> {
         }
     });
 
-    let result: usize = quadrants.iter().product();
+    quadrants.iter().product()
+}
+
+/// finds the first second in which no two robots share a tile, which is when the tree shows up
+fn find_tree_second(robots: &[Robot], config: &Config) -> Option<usize> {
+    let mut positions = robots
+        .iter()
+        .map(|robot| robot.starting_pos)
+        .collect::<Vec<_>>();
+
+    for second in 0..(config.width * config.height) as usize {
+        let unique_positions = positions.iter().collect::<HashSet<_>>();
+        if unique_positions.len() == positions.len() {
+            return Some(second);
+        }
+
+        for (pos, robot) in positions.iter_mut().zip(robots) {
+            *pos = robot.walk(*pos, config);
+        }
+    }
+
+    None
+}
+
+/// the first position that doesn't fit into the room
+fn find_out_of_bounds<'a>(
+    mut positions: impl Iterator<Item = &'a (isize, isize)>,
+    config: &Config,
+) -> Option<(isize, isize)> {
+    positions
+        .find(|pos| !(0..config.width).contains(&pos.0) || !(0..config.height).contains(&pos.1))
+        .copied()
+}
+
+/// renders the amount of robots per tile like the puzzle description does
+fn render_robots(positions: &[(isize, isize)], config: &Config) -> String {
+    let mut grid = vec![vec![0; config.width as usize]; config.height as usize];
+
+    for pos in positions {
+        grid[pos.1 as usize][pos.0 as usize] += 1;
+    }
+
+    grid.iter()
+        .map(|line| {
+            line.iter()
+                .map(|count| match count {
+                    0 => '.',
+                    count => char::from_digit(count % 10, 10).expect("valid digit"),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn repl(robots: &[Robot], mut config: Config) -> Result<(), Error> {
+    let mut positions = robots
+        .iter()
+        .map(|robot| robot.starting_pos)
+        .collect::<Vec<_>>();
+    let mut elapsed = 0;
+
+    let mut lines = stdin().lock().lines();
+
+    loop {
+        print!("> ");
+        stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            [] => {}
+            ["part1"] => {
+                let positions = robots
+                    .iter()
+                    .map(|robot| robot.simulate(&config))
+                    .collect::<Vec<_>>();
+                let result = get_safety_factor(&positions, &config);
+
+                println!("Safety factor after {} seconds: {result}", config.seconds);
+            }
+            ["part2"] => match find_tree_second(robots, &config) {
+                Some(second) => println!("Tree appears after {second} seconds"),
+                None => println!("Robots never stop overlapping"),
+            },
+            ["show"] => {
+                println!("After {elapsed} seconds:");
+                println!("{}", render_robots(&positions, &config));
+            }
+            ["step", amount] => {
+                let Ok(amount) = amount.parse::<usize>() else {
+                    println!("Invalid step amount {amount}");
+                    continue;
+                };
+
+                for (pos, robot) in positions.iter_mut().zip(robots) {
+                    for _ in 0..amount {
+                        *pos = robot.walk(*pos, &config);
+                    }
+                }
+                elapsed += amount;
+
+                let result = get_safety_factor(&positions, &config);
+                println!("After {elapsed} seconds the safety factor is {result}");
+            }
+            ["reset"] => {
+                positions = robots.iter().map(|robot| robot.starting_pos).collect();
+                elapsed = 0;
+            }
+            ["set", key, value] => {
+                let mut new_config = config.clone();
+                if let Err(err) = new_config.set(key, value) {
+                    println!("{err}");
+                    continue;
+                }
+
+                let starting_positions = robots.iter().map(|robot| &robot.starting_pos);
+                if let Some(pos) =
+                    find_out_of_bounds(positions.iter().chain(starting_positions), &new_config)
+                {
+                    println!(
+                        "A robot at {},{} would be outside a {}x{} room",
+                        pos.0, pos.1, new_config.width, new_config.height
+                    );
+                    continue;
+                }
+
+                config = new_config;
+            }
+            ["help"] => {
+                println!("Commands: part1, part2, show, step <n>, reset, set <key> <value>, quit");
+            }
+            ["quit" | "exit"] => break,
+            _ => println!("Unknown command {line}, try help"),
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    let config = config::load(Config::set)?;
+
+    let file = File::open("input.txt")?;
+    let reader = BufReader::new(file);
+
+    let robots = reader
+        .lines()
+        .map(|line| line.expect("valid line"))
+        .map(|line| line.into())
+        .collect::<Vec<Robot>>();
+
+    if let Some(pos) = find_out_of_bounds(robots.iter().map(|robot| &robot.starting_pos), &config) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Robot at {},{} is outside the {}x{} room",
+                pos.0, pos.1, config.width, config.height
+            ),
+        ));
+    }
+
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    if args.get(1).is_some_and(|arg| arg == "repl") {
+        return repl(&robots, config);
+    }
+
+    if let [_, command, name, seconds, ..] = args.as_slice() {
+        if command == "snapshot" {
+            let seconds = seconds.parse::<usize>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Seconds have to be a non-negative number, found {seconds}"),
+                )
+            })?;
+
+            let positions = robots
+                .iter()
+                .map(|robot| {
+                    (0..seconds).fold(robot.starting_pos, |pos, _| robot.walk(pos, &config))
+                })
+                .collect::<Vec<_>>();
+
+            let update = std::env::args().any(|arg| arg == "--update");
+            return snapshot::check(
+                Path::new("snapshots"),
+                name,
+                &render_robots(&positions, &config),
+                update,
+            );
+        }
+    }
+
+    let positions = robots
+        .iter()
+        .map(|robot| robot.simulate(&config))
+        .collect::<Vec<_>>();
+
+    let result = get_safety_factor(&positions, &config);
 
     println!("Result is {result}");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3";
+
+    #[test]
+    fn example_matches_snapshot() {
+        let config = Config {
+            width: 11,
+            height: 7,
+            seconds: 100,
+        };
+
+        let positions = EXAMPLE
+            .lines()
+            .map(|line| Robot::from(line.to_owned()).simulate(&config))
+            .collect::<Vec<_>>();
+
+        snapshot::assert_snapshot!("example_after_100", &render_robots(&positions, &config));
+
+        assert_eq!(get_safety_factor(&positions, &config), 12);
+    }
+
+    #[test]
+    fn velocity_larger_than_room_stays_inside() {
+        let config = Config {
+            width: 11,
+            height: 7,
+            seconds: 1,
+        };
+
+        let robot = Robot::from("p=6,3 v=-25,-3".to_owned());
+        assert_eq!(robot.simulate(&config), (3, 0));
+
+        let robot = Robot::from("p=6,3 v=25,17".to_owned());
+        assert_eq!(robot.simulate(&config), (9, 6));
+    }
+}
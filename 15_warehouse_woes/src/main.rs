@@ -1,7 +1,8 @@
 use core::panic;
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
+    path::Path,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -13,7 +14,7 @@ enum Tile {
     Robot,
 }
 
-fn print_map(map: &[Vec<Tile>]) {
+fn render_map(map: &[Vec<Tile>]) -> String {
     map.iter()
         .map(|line| {
             line.iter()
@@ -26,7 +27,12 @@ fn print_map(map: &[Vec<Tile>]) {
                 })
                 .collect::<String>()
         })
-        .for_each(|line| println!("{line}"));
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn print_map(map: &[Vec<Tile>]) {
+    println!("{}", render_map(map));
 }
 
 #[derive(Debug)]
@@ -84,6 +90,62 @@ fn parse_moves(lines: &[String]) -> Vec<Direction> {
         .collect::<Vec<_>>()
 }
 
+/// needs both sections, known tiles and moves, and exactly one robot
+fn validate(lines: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut inputs = lines.split(|line| line.is_empty());
+
+    let map = inputs.next().unwrap_or_default();
+    let moves = inputs.next().unwrap_or_default();
+
+    if map.is_empty() {
+        problems.push("Map section is missing".to_string());
+    }
+
+    if moves.is_empty() {
+        problems.push("Moves section is missing".to_string());
+    }
+
+    let mut robot_count = 0;
+
+    for (line_idx, line) in map.iter().enumerate() {
+        for (col_idx, c) in line.chars().enumerate() {
+            match c {
+                '#' | '.' | 'O' => {}
+                '@' => robot_count += 1,
+                c => problems.push(format!(
+                    "Unknown tile '{c}' at line {}, column {}",
+                    line_idx + 1,
+                    col_idx + 1
+                )),
+            }
+        }
+    }
+
+    if robot_count != 1 {
+        problems.push(format!(
+            "Expected exactly one robot '@', found {robot_count}"
+        ));
+    }
+
+    let moves_offset = map.len() + 2;
+
+    for (line_idx, line) in moves.iter().enumerate() {
+        for (col_idx, c) in line.chars().enumerate() {
+            if !matches!(c, '<' | '^' | 'v' | '>') {
+                problems.push(format!(
+                    "Unknown move '{c}' at line {}, column {}",
+                    moves_offset + line_idx,
+                    col_idx + 1
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
 fn get_next_pos(pos: (usize, usize), direction: &Direction) -> Option<(usize, usize)> {
     let idx_diff = direction.to_idx_diff();
     let new_y = pos.0.checked_add_signed(idx_diff.0)?;
@@ -158,23 +220,26 @@ fn try_move(
     Some((next_pos.0, next_pos.1, map))
 }
 
-fn main() -> Result<(), Error> {
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-
-    let lines = reader
-        .lines()
-        .map(|line| line.expect("valid line"))
-        .collect::<Vec<_>>();
-
-    let mut inputs = lines.split(|line| line.is_empty());
-
-    let map = inputs.next().expect("map is present");
-    let mut map = parse_map(map);
-
-    let moves = inputs.next().expect("moves are present");
-    let moves = parse_moves(moves);
+fn get_box_pos_sum(map: &[Vec<Tile>]) -> usize {
+    map.iter()
+        .enumerate()
+        .flat_map(|(line_idx, line)| {
+            line.iter()
+                .enumerate()
+                .map(move |(col_idx, tile)| match tile {
+                    Tile::BoxLeft => line_idx * 100 + col_idx,
+                    _ => 0,
+                })
+        })
+        .sum()
+}
 
+/// applies the moves to the map, calling `on_step` with the map after each of them
+fn simulate(
+    mut map: Vec<Vec<Tile>>,
+    moves: &[Direction],
+    mut on_step: impl FnMut(&[Vec<Tile>]),
+) -> Vec<Vec<Tile>> {
     let mut robot_pos = map
         .iter()
         .enumerate()
@@ -187,31 +252,151 @@ fn main() -> Result<(), Error> {
         .map(|(line_idx, col_idx, _)| (line_idx, col_idx))
         .expect("robot is present");
 
-    print_map(&map);
     for direction in moves {
         let (robot_y, robot_x, new_map) =
-            try_move(robot_pos, &direction, &mut map).unwrap_or((robot_pos.0, robot_pos.1, map));
+            try_move(robot_pos, direction, &mut map).unwrap_or((robot_pos.0, robot_pos.1, map));
 
         robot_pos = (robot_y, robot_x);
         map = new_map;
 
-        print_map(&map);
+        on_step(&map);
     }
 
-    let box_pos_sum: usize = map
-        .iter()
-        .enumerate()
-        .flat_map(|(line_idx, line)| {
-            line.iter()
-                .enumerate()
-                .map(move |(col_idx, tile)| match tile {
-                    Tile::BoxLeft => line_idx * 100 + col_idx,
-                    _ => 0,
-                })
-        })
-        .sum();
+    map
+}
+
+fn main() -> Result<(), Error> {
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    let snapshot = match args.as_slice() {
+        [_, command, name, moves, ..] if command == "snapshot" => {
+            let move_count = moves.parse::<usize>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Move count has to be a non-negative number, found {moves}"),
+                )
+            })?;
+
+            Some((name.to_owned(), move_count))
+        }
+        _ => None,
+    };
+    let update_snapshot = std::env::args().any(|arg| arg == "--update");
+
+    let file = File::open("input.txt")?;
+    let reader = BufReader::new(file);
+
+    let lines = reader
+        .lines()
+        .map(|line| line.expect("valid line"))
+        .collect::<Vec<_>>();
+
+    let problems = validate(&lines);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "warehouse is unsolvable",
+        ));
+    }
+
+    let mut inputs = lines.split(|line| line.is_empty());
+
+    let map = inputs.next().expect("map is present");
+    let map = parse_map(map);
+
+    let moves = inputs.next().expect("moves are present");
+    let moves = parse_moves(moves);
+
+    if let Some((name, move_count)) = snapshot {
+        let moves = &moves[..move_count.min(moves.len())];
+        let map = simulate(map, moves, |_| {});
+
+        return snapshot::check(
+            Path::new("snapshots"),
+            &name,
+            &render_map(&map),
+            update_snapshot,
+        );
+    }
+
+    print_map(&map);
+    let map = simulate(map, &moves, print_map);
+
+    let box_pos_sum = get_box_pos_sum(&map);
 
     println!("Box pos sum is {box_pos_sum}");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL_EXAMPLE: &str = "\
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^";
+
+    const LARGE_EXAMPLE: &str = "\
+##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########
+
+<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
+vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
+><>vv>v^v^<>><>>>><^^>vv>v<^^^>>v^v^<^^>v^^>v^<^v>v<>>v^v^<v>v^^<^^vv<
+<<v<^>>^^^^>>>v^<>vvv^><v<<<>^^^vv^<vvv>^>v<^^^^v<>^>vvvv><>>v^<<^^^^^
+^><^><>>><>^^<<^^v>>><^<v>^<vv>>v>>>^v><>^v><<<<v>>v<v<v>vvv>^<><<>^><
+^>><>^v<><^vvv<^^<><v<<<<<><^v<<<><<<^^<v<^^^><^>>^<v^><<<^>>^v<v^v<v^
+>^>>^v>vv>^<<^v<>><<><<v<<v><>v<^vv<<<>^^v^>^^>>><<^v>>v^v><^^>>^<>vv^
+<><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
+^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
+v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
+
+    fn check_example(input: &str, name: &str, move_count: usize) -> Vec<Vec<Tile>> {
+        let lines = input.lines().map(String::from).collect::<Vec<_>>();
+        assert!(validate(&lines).is_empty());
+
+        let mut inputs = lines.split(|line| line.is_empty());
+        let map = parse_map(inputs.next().unwrap());
+        let moves = parse_moves(inputs.next().unwrap());
+
+        let map = simulate(map, &moves[..move_count], |_| {});
+
+        snapshot::assert_snapshot!(name, &render_map(&map));
+
+        map
+    }
+
+    #[test]
+    fn small_example_matches_snapshots() {
+        check_example(SMALL_EXAMPLE, "small_example_after_4", 4);
+        check_example(SMALL_EXAMPLE, "small_example_final", 11);
+    }
+
+    #[test]
+    fn large_example_matches_snapshot() {
+        let map = check_example(LARGE_EXAMPLE, "large_example_final", 700);
+
+        assert_eq!(get_box_pos_sum(&map), 9021);
+    }
+}
@@ -1,9 +1,11 @@
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
 };
 
+use stats::Stats;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 enum Direction {
     Up,
@@ -61,6 +63,35 @@ impl PartialOrd for Point {
     }
 }
 
+/// what a step and a 90 degree turn cost the reindeer
+struct Config {
+    step_cost: usize,
+    turn_cost: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            step_cost: 1,
+            turn_cost: 1000,
+        }
+    }
+}
+
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "step_cost" => self.step_cost = value.parse().map_err(invalid)?,
+            "turn_cost" => self.turn_cost = value.parse().map_err(invalid)?,
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+}
+
 fn walk_direction(pos: (usize, usize), direction: &Direction) -> Option<(usize, usize)> {
     let idx_diff = direction.to_idx_diff();
 
@@ -86,12 +117,93 @@ fn find_coordinates(map: &[Vec<char>], target: char) -> Option<(usize, usize)> {
         .map(|(line_idx, col_idx, _)| (line_idx, col_idx))
 }
 
+fn count_tiles(map: &[Vec<char>], target: char) -> usize {
+    map.iter().flatten().filter(|tile| **tile == target).count()
+}
+
+fn is_reachable(map: &[Vec<char>], start_pos: (usize, usize), end_pos: (usize, usize)) -> bool {
+    let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+    let mut visited: HashSet<(usize, usize)> = HashSet::new();
+
+    queue.push_back(start_pos);
+    visited.insert(start_pos);
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == end_pos {
+            return true;
+        }
+
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            let Some(new_pos) = walk_direction(pos, &direction) else {
+                continue;
+            };
+
+            if matches!(get_on_map(map, new_pos), Some('.' | 'E')) && visited.insert(new_pos) {
+                queue.push_back(new_pos);
+            }
+        }
+    }
+
+    false
+}
+
+/// the maze has to use known tiles and one start and one end, with the end reachable
+fn validate(map: &[Vec<char>]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (line_idx, line) in map.iter().enumerate() {
+        for (col_idx, tile) in line.iter().enumerate() {
+            if !matches!(tile, '#' | '.' | 'S' | 'E') {
+                problems.push(format!(
+                    "Unknown tile '{tile}' at line {}, column {}",
+                    line_idx + 1,
+                    col_idx + 1
+                ));
+            }
+        }
+    }
+
+    let start_count = count_tiles(map, 'S');
+    if start_count != 1 {
+        problems.push(format!(
+            "Expected exactly one start 'S', found {start_count}"
+        ));
+    }
+
+    let end_count = count_tiles(map, 'E');
+    if end_count != 1 {
+        problems.push(format!("Expected exactly one end 'E', found {end_count}"));
+    }
+
+    if let (Some(start_pos), Some(end_pos)) =
+        (find_coordinates(map, 'S'), find_coordinates(map, 'E'))
+    {
+        if !is_reachable(map, start_pos, end_pos) {
+            problems.push(format!(
+                "End at line {}, column {} is not reachable from start at line {}, column {}",
+                end_pos.0 + 1,
+                end_pos.1 + 1,
+                start_pos.0 + 1,
+                start_pos.1 + 1
+            ));
+        }
+    }
+
+    problems
+}
+
 fn add_to_queue(
     map: &[Vec<char>],
     queue: &mut BinaryHeap<Point>,
     point: &Point,
     direction: &Direction,
     cost_increase: usize,
+    stats: &mut Stats,
 ) {
     if let Some(new_pos) = walk_direction(point.pos, direction) {
         if let Some('.' | 'E') = get_on_map(map, new_pos) {
@@ -110,6 +222,7 @@ fn add_to_queue(
                 enter_direction: *direction,
                 previous_points: path,
             });
+            stats.incr("States pushed");
         }
     }
 }
@@ -118,6 +231,8 @@ fn get_min_cost_tiles(
     map: &[Vec<char>],
     start_pos: (usize, usize),
     end_pos: (usize, usize),
+    config: &Config,
+    stats: &mut Stats,
 ) -> usize {
     let mut queue: BinaryHeap<Point> = BinaryHeap::new();
     let mut visited_points: HashMap<(usize, usize, Direction), usize> = HashMap::new();
@@ -128,10 +243,13 @@ fn get_min_cost_tiles(
         cost: 0,
         previous_points: HashSet::new(),
     });
+    stats.incr("States pushed");
 
     let mut paths_to_finish: Vec<(usize, HashSet<(usize, usize)>)> = Vec::new();
 
     while let Some(point) = queue.pop() {
+        stats.incr("States popped");
+
         if point.pos == end_pos {
             let cost = point.cost;
 
@@ -147,6 +265,7 @@ fn get_min_cost_tiles(
                 .get(&(point.pos.0, point.pos.1, point.enter_direction))
                 .unwrap_or(&usize::MAX)
         {
+            stats.incr("States pruned");
             continue;
         }
 
@@ -156,13 +275,19 @@ fn get_min_cost_tiles(
         );
 
         [
-            (point.enter_direction, 1),
-            (point.enter_direction.turn_clockwise(), 1001),
-            (point.enter_direction.turn_counter_clockwise(), 1001),
+            (point.enter_direction, config.step_cost),
+            (
+                point.enter_direction.turn_clockwise(),
+                config.turn_cost + config.step_cost,
+            ),
+            (
+                point.enter_direction.turn_counter_clockwise(),
+                config.turn_cost + config.step_cost,
+            ),
         ]
         .iter()
         .for_each(|(direction, cost_increase)| {
-            add_to_queue(map, &mut queue, &point, direction, *cost_increase)
+            add_to_queue(map, &mut queue, &point, direction, *cost_increase, stats)
         });
     }
 
@@ -186,6 +311,8 @@ fn get_min_cost_tiles(
 }
 
 fn main() -> Result<(), Error> {
+    let config = config::load(Config::set)?;
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
 
@@ -194,12 +321,24 @@ fn main() -> Result<(), Error> {
         .map(|line| line.expect("valid line").chars().collect::<Vec<_>>())
         .collect::<Vec<_>>();
 
+    let problems = validate(&map);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+
+        return Err(Error::new(ErrorKind::InvalidData, "maze is unsolvable"));
+    }
+
     let start_pos = find_coordinates(&map, 'S').expect("start is present");
     let end_pos = find_coordinates(&map, 'E').expect("end is present");
 
-    let min_cost_tiles = get_min_cost_tiles(&map, start_pos, end_pos);
+    let mut stats = Stats::new(&["States pushed", "States popped", "States pruned"]);
+    let min_cost_tiles = get_min_cost_tiles(&map, start_pos, end_pos, &config, &mut stats);
 
     println!("Min cost tiles is {min_cost_tiles}");
 
+    stats.print_if_requested();
+
     Ok(())
 }
@@ -2,15 +2,60 @@ use core::panic;
 use std::{
     collections::{HashMap, VecDeque},
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{stdin, stdout, BufRead, BufReader, Error, ErrorKind, Write},
 };
 
-const GRID_WIDTH: usize = 71;
-const GRID_HEIGHT: usize = 71;
+use stats::Stats;
 
 const DIRECTIONS: &[(isize, isize); 4] = &[(-1, 0), (1, 0), (0, -1), (0, 1)];
 
-fn get_shortest_path(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT]) -> Option<usize> {
+/// memory space size and how many bytes fall before part 1 looks for a path
+#[derive(Clone)]
+struct Config {
+    width: usize,
+    height: usize,
+    bytes: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 71,
+            height: 71,
+            bytes: 1024,
+        }
+    }
+}
+
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "width" | "height" => {
+                let size = value
+                    .parse()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| format!("{key} has to be at least 1, found {value}"))?;
+
+                if key == "width" {
+                    self.width = size;
+                } else {
+                    self.height = size;
+                }
+            }
+            "bytes" => self.bytes = value.parse().map_err(invalid)?,
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+}
+
+fn get_shortest_path(grid: &[Vec<bool>], stats: &mut Stats) -> Option<usize> {
+    stats.incr("BFS runs");
+
     let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
     let mut tile_dist: HashMap<(usize, usize), usize> = HashMap::new();
 
@@ -18,7 +63,9 @@ fn get_shortest_path(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT]) -> Option<usize>
     tile_dist.insert((0, 0), 0);
 
     while let Some(pos) = queue.pop_front() {
-        if pos.0 == GRID_HEIGHT - 1 && pos.1 == GRID_WIDTH - 1 {
+        stats.incr("BFS expansions");
+
+        if pos.0 == grid.len() - 1 && pos.1 == grid[pos.0].len() - 1 {
             return Some(*tile_dist.get(&pos).expect("goal has cost"));
         }
 
@@ -47,28 +94,193 @@ fn get_shortest_path(grid: &[[bool; GRID_WIDTH]; GRID_HEIGHT]) -> Option<usize>
     None
 }
 
+/// parses a falling byte given as `x,y` into a (y, x) grid position
+fn parse_byte(line: &str) -> (usize, usize) {
+    let Some((x, y)) = line.split_once(",") else {
+        panic!("Expected line to follow x,y. Got {line}")
+    };
+
+    (
+        y.parse::<usize>().expect("valid usize"),
+        x.parse::<usize>().expect("valid usize"),
+    )
+}
+
+/// the first byte that falls outside the memory space
+fn find_out_of_bounds(bytes: &[(usize, usize)], config: &Config) -> Option<(usize, usize)> {
+    bytes
+        .iter()
+        .find(|(y, x)| *y >= config.height || *x >= config.width)
+        .copied()
+}
+
+fn build_grid(bytes: &[(usize, usize)], config: &Config) -> Vec<Vec<bool>> {
+    let mut grid = vec![vec![false; config.width]; config.height];
+
+    for (y, x) in bytes {
+        grid[*y][*x] = true;
+    }
+
+    grid
+}
+
+fn render_grid(grid: &[Vec<bool>]) -> String {
+    grid.iter()
+        .map(|line| {
+            line.iter()
+                .map(|is_corrupted| if *is_corrupted { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// finds the first byte after which the exit cannot be reached anymore
+fn find_blocking_byte(
+    bytes: &[(usize, usize)],
+    config: &Config,
+    stats: &mut Stats,
+) -> Option<(usize, usize)> {
+    let mut grid = build_grid(&[], config);
+
+    for (y, x) in bytes {
+        grid[*y][*x] = true;
+
+        if get_shortest_path(&grid, stats).is_none() {
+            return Some((*y, *x));
+        }
+    }
+
+    None
+}
+
+/// the shortest path once `config.bytes` bytes have fallen
+fn print_part1(bytes: &[(usize, usize)], config: &Config, stats: &mut Stats) {
+    let fallen = config.bytes.min(bytes.len());
+    let grid = build_grid(&bytes[..fallen], config);
+
+    match get_shortest_path(&grid, stats) {
+        Some(shortest_path) => println!("Shortest path after {fallen} bytes is {shortest_path}"),
+        None => println!("Grid not traversible after {fallen} bytes"),
+    }
+}
+
+fn print_part2(bytes: &[(usize, usize)], config: &Config, stats: &mut Stats) {
+    match find_blocking_byte(bytes, config, stats) {
+        Some((y, x)) => println!("Grid not traversible after: {x},{y}"),
+        None => println!("Grid stays traversible"),
+    }
+}
+
+fn repl(bytes: &[(usize, usize)], mut config: Config) -> Result<(), Error> {
+    let mut fallen = 0;
+
+    let mut stats = Stats::new(&["BFS runs", "BFS expansions"]);
+
+    let mut lines = stdin().lock().lines();
+
+    loop {
+        print!("> ");
+        stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.as_slice() {
+            [] => {}
+            ["part1"] => print_part1(bytes, &config, &mut stats),
+            ["part2"] => print_part2(bytes, &config, &mut stats),
+            ["show"] => {
+                println!("After {fallen} bytes:");
+                println!("{}", render_grid(&build_grid(&bytes[..fallen], &config)));
+            }
+            ["path"] => {
+                let grid = build_grid(&bytes[..fallen], &config);
+
+                match get_shortest_path(&grid, &mut stats) {
+                    Some(shortest_path) => println!("Shortest path is {shortest_path}"),
+                    None => println!("Grid not traversible"),
+                }
+            }
+            ["step", amount] => {
+                let Ok(amount) = amount.parse::<usize>() else {
+                    println!("Invalid step amount {amount}");
+                    continue;
+                };
+
+                fallen = (fallen + amount).min(bytes.len());
+                println!("{fallen} of {} bytes have fallen", bytes.len());
+            }
+            ["reset"] => fallen = 0,
+            ["set", key, value] => {
+                let mut new_config = config.clone();
+                if let Err(err) = new_config.set(key, value) {
+                    println!("{err}");
+                    continue;
+                }
+
+                if let Some((y, x)) = find_out_of_bounds(bytes, &new_config) {
+                    println!(
+                        "Byte {x},{y} would fall outside a {}x{} memory space",
+                        new_config.width, new_config.height
+                    );
+                    continue;
+                }
+
+                config = new_config;
+            }
+            ["stats"] => stats.print(),
+            ["help"] => {
+                println!(
+                    "Commands: part1, part2, show, path, step <n>, reset, set <key> <value>, stats, quit"
+                );
+            }
+            ["quit" | "exit"] => break,
+            _ => println!("Unknown command {line}, try help"),
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
-    let mut grid = [[false; GRID_WIDTH]; GRID_HEIGHT];
+    let config = config::load(Config::set)?;
 
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
-    let mut lines = reader.lines();
 
-    while let Some(Ok(line)) = lines.next() {
-        let Some((x, y)) = line.split_once(",") else {
-            panic!("Expected line to follow x,y. Got {line}")
-        };
+    let bytes = reader
+        .lines()
+        .map(|line| parse_byte(&line.expect("valid line")))
+        .collect::<Vec<_>>();
 
-        grid[y.parse::<usize>().expect("valid usize")][x.parse::<usize>().expect("valid usize")] =
-            true;
+    if let Some((y, x)) = find_out_of_bounds(&bytes, &config) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Byte {x},{y} is outside the {}x{} memory space",
+                config.width, config.height
+            ),
+        ));
+    }
 
-        if let Some(shortest_path) = get_shortest_path(&grid) {
-            println!("Shortest path is {shortest_path}");
-        } else {
-            println!("Grid not traversible possible after: {line}");
-            break;
-        };
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    if args.get(1).is_some_and(|arg| arg == "repl") {
+        return repl(&bytes, config);
     }
 
+    let mut stats = Stats::new(&["BFS runs", "BFS expansions"]);
+
+    print_part1(&bytes, &config, &mut stats);
+    print_part2(&bytes, &config, &mut stats);
+
+    stats.print_if_requested();
+
     Ok(())
 }
@@ -1,47 +1,728 @@
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader, Error},
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Lines, Write},
+    path::{Path, PathBuf},
     vec,
 };
 
+/// amount of numbers per column kept in memory before a sorted run is spilled to disk
+const DEFAULT_RUN_SIZE: usize = 1_000_000;
+
+/// amount of sorted runs merged at once, more runs are merged over several passes
+const MAX_OPEN_RUNS: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+enum Metric {
+    Distance,
+    Similarity,
+    SharedIds,
+    SymmetricDifference,
+}
+
+static ALL_METRICS: &[Metric] = &[
+    Metric::Distance,
+    Metric::Similarity,
+    Metric::SharedIds,
+    Metric::SymmetricDifference,
+];
+
+impl Metric {
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::Distance => "distance",
+            Metric::Similarity => "similarity",
+            Metric::SharedIds => "shared-ids",
+            Metric::SymmetricDifference => "symmetric-difference",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Metric> {
+        ALL_METRICS
+            .iter()
+            .find(|metric| metric.name() == name)
+            .copied()
+    }
+}
+
+fn count_occurences(nums: &[usize]) -> HashMap<usize, usize> {
+    nums.iter().fold(HashMap::new(), |mut acc, curr| {
+        acc.insert(*curr, acc.get(curr).unwrap_or(&0) + 1);
+        acc
+    })
+}
+
+/// compares the left and right location ID lists by one of several metrics
+struct ListComparison<'a> {
+    left_nums: &'a [usize],
+    right_nums: &'a [usize],
+}
+
+impl<'a> ListComparison<'a> {
+    fn new(left_nums: &'a [usize], right_nums: &'a [usize]) -> Self {
+        Self {
+            left_nums,
+            right_nums,
+        }
+    }
+
+    fn compute(&self, metric: Metric) -> usize {
+        match metric {
+            Metric::Distance => self.distance(),
+            Metric::Similarity => self.similarity(),
+            Metric::SharedIds => self.shared_id_count(),
+            Metric::SymmetricDifference => self.symmetric_difference_count(),
+        }
+    }
+
+    /// pairs up the sorted lists and sums the differences of each pair
+    fn distance(&self) -> usize {
+        let mut left_nums = self.left_nums.to_vec();
+        let mut right_nums = self.right_nums.to_vec();
+
+        left_nums.sort_unstable();
+        right_nums.sort_unstable();
+
+        left_nums
+            .iter()
+            .zip(right_nums.iter())
+            .map(|(left, right)| left.abs_diff(*right))
+            .sum()
+    }
+
+    /// sums every left number multiplied by how often it occurs in the right list
+    fn similarity(&self) -> usize {
+        let right_num_occurence = count_occurences(self.right_nums);
+
+        self.left_nums
+            .iter()
+            .map(|num| num * right_num_occurence.get(num).unwrap_or(&0))
+            .sum()
+    }
+
+    /// counts the distinct IDs present in both lists
+    fn shared_id_count(&self) -> usize {
+        let left_ids: HashSet<usize> = self.left_nums.iter().copied().collect();
+        let right_ids: HashSet<usize> = self.right_nums.iter().copied().collect();
+
+        left_ids.intersection(&right_ids).count()
+    }
+
+    /// counts the distinct IDs present in only one of the lists
+    fn symmetric_difference_count(&self) -> usize {
+        let left_ids: HashSet<usize> = self.left_nums.iter().copied().collect();
+        let right_ids: HashSet<usize> = self.right_nums.iter().copied().collect();
+
+        left_ids.symmetric_difference(&right_ids).count()
+    }
+
+    /// lists the IDs of the left list that never occur in the right one, with their counts
+    fn missing_from_right(&self) -> Vec<(usize, usize)> {
+        let right_ids: HashSet<usize> = self.right_nums.iter().copied().collect();
+
+        let mut missing = count_occurences(self.left_nums)
+            .into_iter()
+            .filter(|(id, _)| !right_ids.contains(id))
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+
+        missing
+    }
+}
+
+fn parse_line(line: &str, line_idx: usize) -> Result<Vec<usize>, Error> {
+    line.split_whitespace()
+        .map(|num| num.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Line {}: {err}", line_idx + 1),
+            )
+        })
+}
+
+fn check_column_count(nums: &[usize], column_count: usize, line_idx: usize) -> Result<(), Error> {
+    if nums.len() == column_count {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "Line {}: expected {column_count} columns, found {}",
+            line_idx + 1,
+            nums.len()
+        ),
+    ))
+}
+
+fn parse_columns(lines: &[String]) -> Result<Vec<Vec<usize>>, Error> {
+    let mut columns: Vec<Vec<usize>> = vec![];
+
+    for (line_idx, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let nums = parse_line(line, line_idx)?;
+
+        if columns.is_empty() {
+            columns = vec![vec![]; nums.len()];
+        }
+
+        check_column_count(&nums, columns.len(), line_idx)?;
+
+        for (column, num) in columns.iter_mut().zip(nums) {
+            column.push(num);
+        }
+    }
+
+    Ok(columns)
+}
+
+/// directory that is removed with everything in it once dropped, also when sorting fails
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn create(path: PathBuf) -> Result<Self, Error> {
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// streams the numbers of a sorted file, one number per line
+struct SortedFileReader {
+    lines: Lines<BufReader<File>>,
+}
+
+impl SortedFileReader {
+    fn open(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            lines: BufReader::new(File::open(path)?).lines(),
+        })
+    }
+}
+
+impl Iterator for SortedFileReader {
+    type Item = Result<usize, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next().map(|line| {
+            let line = line?;
+
+            line.parse().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("sorted file contains {line}, expected a number"),
+                )
+            })
+        })
+    }
+}
+
+/// sorts every input column on disk, keeping at most `run_size` numbers per column in memory
+struct ExternalSorter {
+    dir: PathBuf,
+    run_size: usize,
+    buffers: Vec<Vec<usize>>,
+    runs: Vec<Vec<PathBuf>>,
+}
+
+impl ExternalSorter {
+    fn new(dir: PathBuf, column_count: usize, run_size: usize) -> Self {
+        Self {
+            dir,
+            run_size,
+            buffers: vec![vec![]; column_count],
+            runs: vec![vec![]; column_count],
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    fn push(&mut self, column_idx: usize, num: usize) -> Result<(), Error> {
+        self.buffers[column_idx].push(num);
+
+        if self.buffers[column_idx].len() >= self.run_size {
+            self.spill(column_idx)?;
+        }
+
+        Ok(())
+    }
+
+    /// writes the buffered numbers of a column to disk as one sorted run
+    fn spill(&mut self, column_idx: usize) -> Result<(), Error> {
+        let buffer = &mut self.buffers[column_idx];
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
+        buffer.sort_unstable();
+
+        let runs = &mut self.runs[column_idx];
+        let path = self
+            .dir
+            .join(format!("column-{column_idx}-run-{}.txt", runs.len()));
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for num in buffer.iter() {
+            writeln!(writer, "{num}")?;
+        }
+        writer.flush()?;
+
+        buffer.clear();
+        runs.push(path);
+
+        Ok(())
+    }
+
+    /// merges the runs of every column into one sorted file per column
+    fn finish(mut self) -> Result<Vec<PathBuf>, Error> {
+        let mut sorted_files = vec![];
+
+        for column_idx in 0..self.column_count() {
+            self.spill(column_idx)?;
+
+            sorted_files.push(self.merge_column(column_idx)?);
+        }
+
+        Ok(sorted_files)
+    }
+
+    /// merges the runs of a column in passes of at most `MAX_OPEN_RUNS` runs until one file is left
+    fn merge_column(&mut self, column_idx: usize) -> Result<PathBuf, Error> {
+        let mut runs = std::mem::take(&mut self.runs[column_idx]);
+        let mut pass = 0;
+
+        while runs.len() > MAX_OPEN_RUNS {
+            let mut merged_runs = vec![];
+
+            for (chunk_idx, chunk) in runs.chunks(MAX_OPEN_RUNS).enumerate() {
+                let path = self.dir.join(format!(
+                    "column-{column_idx}-pass-{pass}-run-{chunk_idx}.txt"
+                ));
+                merge_runs(chunk, &path)?;
+
+                for run in chunk {
+                    fs::remove_file(run)?;
+                }
+
+                merged_runs.push(path);
+            }
+
+            runs = merged_runs;
+            pass += 1;
+        }
+
+        let path = self.dir.join(format!("column-{column_idx}.txt"));
+        merge_runs(&runs, &path)?;
+
+        for run in &runs {
+            fs::remove_file(run)?;
+        }
+
+        Ok(path)
+    }
+}
+
+fn merge_runs(runs: &[PathBuf], target: &Path) -> Result<(), Error> {
+    let mut readers = runs
+        .iter()
+        .map(|run| SortedFileReader::open(run))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = BinaryHeap::new();
+    for (run_idx, reader) in readers.iter_mut().enumerate() {
+        if let Some(num) = reader.next().transpose()? {
+            heap.push(Reverse((num, run_idx)));
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(target)?);
+
+    while let Some(Reverse((num, run_idx))) = heap.pop() {
+        writeln!(writer, "{num}")?;
+
+        if let Some(next_num) = readers[run_idx].next().transpose()? {
+            heap.push(Reverse((next_num, run_idx)));
+        }
+    }
+
+    writer.flush()
+}
+
+/// collapses a sorted stream into its distinct values and how often each occurs
+fn group_counts(
+    nums: impl Iterator<Item = Result<usize, Error>>,
+) -> impl Iterator<Item = Result<(usize, usize), Error>> {
+    let mut nums = nums.peekable();
+
+    std::iter::from_fn(move || {
+        let num = match nums.next()? {
+            Ok(num) => num,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut count = 1;
+
+        while nums
+            .next_if(|next| matches!(next, Ok(next) if *next == num))
+            .is_some()
+        {
+            count += 1;
+        }
+
+        Some(Ok((num, count)))
+    })
+}
+
+/// walks two sorted files in lockstep, reporting every distinct value with its count in both
+fn merge_join(
+    left: &Path,
+    right: &Path,
+    mut on_value: impl FnMut(usize, usize, usize),
+) -> Result<(), Error> {
+    let mut left_groups = group_counts(SortedFileReader::open(left)?);
+    let mut right_groups = group_counts(SortedFileReader::open(right)?);
+
+    let mut left_group = left_groups.next().transpose()?;
+    let mut right_group = right_groups.next().transpose()?;
+
+    loop {
+        match (left_group, right_group) {
+            (Some((left_num, left_count)), Some((right_num, right_count))) => {
+                match left_num.cmp(&right_num) {
+                    Ordering::Less => {
+                        on_value(left_num, left_count, 0);
+                        left_group = left_groups.next().transpose()?;
+                    }
+                    Ordering::Greater => {
+                        on_value(right_num, 0, right_count);
+                        right_group = right_groups.next().transpose()?;
+                    }
+                    Ordering::Equal => {
+                        on_value(left_num, left_count, right_count);
+                        left_group = left_groups.next().transpose()?;
+                        right_group = right_groups.next().transpose()?;
+                    }
+                }
+            }
+            (Some((left_num, left_count)), None) => {
+                on_value(left_num, left_count, 0);
+                left_group = left_groups.next().transpose()?;
+            }
+            (None, Some((right_num, right_count))) => {
+                on_value(right_num, 0, right_count);
+                right_group = right_groups.next().transpose()?;
+            }
+            (None, None) => return Ok(()),
+        }
+    }
+}
+
+/// computes the same metrics as `ListComparison` by streaming two sorted files
+fn compare_sorted_files(left: &Path, right: &Path, metric: Metric) -> Result<usize, Error> {
+    if let Metric::Distance = metric {
+        let mut distance = 0;
+
+        for (left, right) in SortedFileReader::open(left)?.zip(SortedFileReader::open(right)?) {
+            distance += left?.abs_diff(right?);
+        }
+
+        return Ok(distance);
+    }
+
+    let mut result = 0;
+
+    merge_join(left, right, |num, left_count, right_count| {
+        result += match metric {
+            Metric::Distance => unreachable!("distance is computed without a join"),
+            Metric::Similarity => num * left_count * right_count,
+            Metric::SharedIds => usize::from(left_count > 0 && right_count > 0),
+            Metric::SymmetricDifference => usize::from((left_count > 0) != (right_count > 0)),
+        }
+    })?;
+
+    Ok(result)
+}
+
+fn missing_from_sorted_file(left: &Path, right: &Path) -> Result<Vec<(usize, usize)>, Error> {
+    let mut missing = vec![];
+
+    merge_join(left, right, |num, left_count, right_count| {
+        if left_count > 0 && right_count == 0 {
+            missing.push((num, left_count));
+        }
+    })?;
+
+    Ok(missing)
+}
+
+/// sorts the input columns on disk in runs of `run_size` numbers and returns one file per column
+fn sort_input_externally(
+    reader: impl BufRead,
+    dir: &Path,
+    run_size: usize,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut sorter: Option<ExternalSorter> = None;
+
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let nums = parse_line(&line, line_idx)?;
+
+        let sorter = sorter
+            .get_or_insert_with(|| ExternalSorter::new(dir.to_path_buf(), nums.len(), run_size));
+        check_column_count(&nums, sorter.column_count(), line_idx)?;
+
+        for (column_idx, num) in nums.into_iter().enumerate() {
+            sorter.push(column_idx, num)?;
+        }
+    }
+
+    match sorter {
+        Some(sorter) => sorter.finish(),
+        None => Ok(vec![]),
+    }
+}
+
+fn print_matrix(
+    column_count: usize,
+    metric: Metric,
+    mut compare: impl FnMut(usize, usize) -> Result<usize, Error>,
+) -> Result<(), Error> {
+    let matrix = (0..column_count)
+        .map(|left_idx| {
+            (0..column_count)
+                .map(|right_idx| compare(left_idx, right_idx))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let width = matrix
+        .iter()
+        .flatten()
+        .map(|value| value.to_string().len())
+        .chain([column_count.to_string().len()])
+        .max()
+        .unwrap_or(1);
+
+    println!("{} matrix:", metric.name());
+
+    let header = (1..=column_count)
+        .map(|column_nr| format!("{column_nr:>width$}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!("{:>width$} {header}", "");
+
+    for (row_idx, row) in matrix.iter().enumerate() {
+        let values = row
+            .iter()
+            .map(|value| format!("{value:>width$}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("{:>width$} {values}", row_idx + 1);
+    }
+
+    Ok(())
+}
+
+/// prints a single value for two lists and a matrix across all pairs for more
+fn print_metrics(
+    column_count: usize,
+    metrics: &[Metric],
+    mut compare: impl FnMut(usize, usize, Metric) -> Result<usize, Error>,
+) -> Result<(), Error> {
+    for metric in metrics {
+        if column_count == 2 {
+            println!("{} is {}", metric.name(), compare(0, 1, *metric)?);
+        } else {
+            print_matrix(column_count, *metric, |left_idx, right_idx| {
+                compare(left_idx, right_idx, *metric)
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_mismatches(
+    column_count: usize,
+    mut missing_from: impl FnMut(usize, usize) -> Result<Vec<(usize, usize)>, Error>,
+) -> Result<(), Error> {
+    for left_idx in 0..column_count {
+        for right_idx in 0..column_count {
+            if left_idx == right_idx {
+                continue;
+            }
+
+            let missing = missing_from(left_idx, right_idx)?;
+            if missing.is_empty() {
+                continue;
+            }
+
+            println!(
+                "IDs in list {} missing from list {}:",
+                left_idx + 1,
+                right_idx + 1
+            );
+            for (id, count) in missing {
+                println!("  {id} x{count}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
+    let metric_arg =
+        std::env::args().find_map(|arg| arg.strip_prefix("--metric=").map(str::to_string));
+
+    let metrics = match metric_arg {
+        Some(name) => {
+            let Some(metric) = Metric::parse(&name) else {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unknown metric {name}"),
+                ));
+            };
+
+            vec![metric]
+        }
+        None => ALL_METRICS.to_vec(),
+    };
+
+    let show_mismatches = std::env::args().any(|arg| arg == "--mismatches");
+
     let input = File::open("input.txt")?;
     let reader = BufReader::new(input);
-    let mut lines = reader.lines();
-
-    let mut left_nums: Vec<usize> = vec![];
-    let mut right_nums: Vec<usize> = vec![];
-
-    while let Some(Ok(line)) = lines.next() {
-        let mut nums = line.split_whitespace();
-
-        left_nums.push(
-            nums.next()
-                .expect("left part")
-                .parse()
-                .expect("valid number"),
-        );
-        right_nums.push(
-            nums.next()
-                .expect("right part")
-                .parse()
-                .expect("valid number"),
-        );
-    }
-
-    let right_num_occurence: HashMap<usize, usize> =
-        right_nums.iter().fold(HashMap::new(), |mut acc, curr| {
-            acc.insert(*curr, acc.get(curr).unwrap_or(&0) + 1);
-            acc
-        });
-
-    let sum: usize = left_nums
-        .iter()
-        .map(|num| num * right_num_occurence.get(num).unwrap_or(&0))
-        .sum();
 
-    println!("Sum is {sum}");
+    if std::env::args().any(|arg| arg == "--external") {
+        let run_size = match std::env::args().find_map(|arg| {
+            arg.strip_prefix("--run-size=")
+                .map(|run_size| run_size.parse::<usize>())
+        }) {
+            Some(Ok(run_size)) if run_size > 0 => run_size,
+            Some(_) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "run size must be a positive number",
+                ))
+            }
+            None => DEFAULT_RUN_SIZE,
+        };
+
+        let dir =
+            TempDir::create(std::env::temp_dir().join(format!("hysteria-{}", std::process::id())))?;
+
+        let sorted_files = sort_input_externally(reader, dir.path(), run_size)?;
+
+        print_metrics(
+            sorted_files.len(),
+            &metrics,
+            |left_idx, right_idx, metric| {
+                compare_sorted_files(&sorted_files[left_idx], &sorted_files[right_idx], metric)
+            },
+        )?;
+
+        if show_mismatches {
+            print_mismatches(sorted_files.len(), |left_idx, right_idx| {
+                missing_from_sorted_file(&sorted_files[left_idx], &sorted_files[right_idx])
+            })?;
+        }
+
+        return Ok(());
+    }
+
+    let lines = reader.lines().collect::<Result<Vec<_>, _>>()?;
+    let columns = parse_columns(&lines)?;
+
+    print_metrics(columns.len(), &metrics, |left_idx, right_idx, metric| {
+        Ok(ListComparison::new(&columns[left_idx], &columns[right_idx]).compute(metric))
+    })?;
+
+    if show_mismatches {
+        print_mismatches(columns.len(), |left_idx, right_idx| {
+            Ok(ListComparison::new(&columns[left_idx], &columns[right_idx]).missing_from_right())
+        })?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// three columns with plenty of repeated IDs, so every metric has something to find
+    fn random_input(line_count: usize) -> String {
+        let mut state: u64 = 0x1_2024;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            state % 60
+        };
+
+        (0..line_count)
+            .map(|_| format!("{}   {}   {}\n", next(), next(), next() + 20))
+            .collect()
+    }
+
+    #[test]
+    fn external_sort_matches_in_memory() {
+        let input = random_input(500);
+        let lines = input.lines().map(String::from).collect::<Vec<_>>();
+        let columns = parse_columns(&lines).unwrap();
+
+        let dir = TempDir::create(
+            std::env::temp_dir().join(format!("hysteria-test-{}", std::process::id())),
+        )
+        .unwrap();
+
+        // 500 numbers in runs of 3 need two merge passes before the final one
+        let sorted_files = sort_input_externally(input.as_bytes(), dir.path(), 3).unwrap();
+        assert_eq!(sorted_files.len(), columns.len());
+
+        for left_idx in 0..columns.len() {
+            for right_idx in 0..columns.len() {
+                let comparison = ListComparison::new(&columns[left_idx], &columns[right_idx]);
+                let (left, right) = (&sorted_files[left_idx], &sorted_files[right_idx]);
+
+                for metric in ALL_METRICS {
+                    assert_eq!(
+                        compare_sorted_files(left, right, *metric).unwrap(),
+                        comparison.compute(*metric),
+                        "{} of columns {left_idx} and {right_idx}",
+                        metric.name()
+                    );
+                }
+
+                assert_eq!(
+                    missing_from_sorted_file(left, right).unwrap(),
+                    comparison.missing_from_right()
+                );
+            }
+        }
+    }
+}
@@ -1,31 +1,281 @@
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
 };
 
-fn all_have_same_sign(entries: &Vec<isize>) -> bool {
-    if entries[0].is_positive() {
-        entries.iter().all(|entry| entry.is_positive())
+/// the rules a report has to follow
+struct Config {
+    min_step: isize,
+    max_step: isize,
+    /// when false, neighbouring levels may also be equal instead of strictly in- or decreasing
+    strict: bool,
+    tolerance: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            min_step: 1,
+            max_step: 3,
+            strict: true,
+            tolerance: 1,
+        }
+    }
+}
+
+impl Config {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "min_step" | "max_step" => {
+                let step = value
+                    .parse()
+                    .ok()
+                    .filter(|step| *step >= 0)
+                    .ok_or_else(|| format!("{key} has to be at least 0, found {value}"))?;
+
+                if key == "min_step" {
+                    self.min_step = step;
+                } else {
+                    self.max_step = step;
+                }
+            }
+            "tolerance" => self.tolerance = value.parse().map_err(invalid)?,
+            "strict" => {
+                self.strict = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value {value} for {key}"))?
+            }
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+
+    /// checked once everything is loaded, so the two steps can be set in any order
+    fn check_steps(&self) -> Result<(), Error> {
+        if self.min_step > self.max_step {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "min_step {} is larger than max_step {}",
+                    self.min_step, self.max_step
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn all_have_same_sign(entries: &[isize], strict: bool) -> bool {
+    let Some(first) = entries.first() else {
+        return true;
+    };
+
+    if strict {
+        if first.is_positive() {
+            entries.iter().all(|entry| entry.is_positive())
+        } else {
+            entries.iter().all(|entry| entry.is_negative())
+        }
     } else {
-        entries.iter().all(|entry| entry.is_negative())
+        entries.iter().all(|entry| !entry.is_negative())
+            || entries.iter().all(|entry| !entry.is_positive())
     }
 }
 
-fn is_valid_record(record: &Vec<isize>) -> bool {
+fn is_allowed_step_size(diff: isize, config: &Config) -> bool {
+    let step = diff.abs();
+
+    (step >= config.min_step && step <= config.max_step) || (!config.strict && step == 0)
+}
+
+/// an empty report has nothing to check and counts as unsafe, while a single level is always safe
+/// and two levels are safe if the one step between them is allowed
+fn is_valid_record(record: &[isize], config: &Config) -> bool {
+    if record.is_empty() {
+        return false;
+    }
+
     let diffs: Vec<isize> = record
         .windows(2)
         .map(|window| {
-            let a = window.get(0).expect("left");
+            let a = window.first().expect("left");
             let b = window.get(1).expect("right");
 
             a - b
         })
         .collect();
 
-    diffs.iter().all(|diff| diff.abs() > 0 && diff.abs() < 4) && all_have_same_sign(&diffs)
+    diffs.iter().all(|diff| is_allowed_step_size(*diff, config))
+        && all_have_same_sign(&diffs, config.strict)
+}
+
+fn is_safe_step(from: isize, to: isize, direction: isize, config: &Config) -> bool {
+    let diff = (to - from) * direction;
+
+    (diff > 0 || (!config.strict && diff == 0)) && is_allowed_step_size(diff, config)
+}
+
+/// finds the fewest levels, at most `k`, whose removal makes the record safe, in O(n * k²).
+/// `previous[idx][removed]` is set if a safe sequence can end at `idx` after `removed` removals
+/// and holds the level kept before `idx`, or `None` if `idx` is the first kept level.
+/// removing every level leaves an empty report, which is never safe
+fn dampen(record: &[isize], k: usize, config: &Config) -> Option<Vec<usize>> {
+    [1, -1]
+        .into_iter()
+        .filter_map(|direction| {
+            let mut previous: Vec<Vec<Option<Option<usize>>>> =
+                vec![vec![None; k + 1]; record.len()];
+
+            for idx in 0..record.len() {
+                if idx <= k {
+                    previous[idx][idx] = Some(None);
+                }
+
+                for prev_idx in idx.saturating_sub(k + 1)..idx {
+                    if !is_safe_step(record[prev_idx], record[idx], direction, config) {
+                        continue;
+                    }
+
+                    let skipped = idx - prev_idx - 1;
+
+                    for removed in 0..=(k - skipped) {
+                        if previous[prev_idx][removed].is_some()
+                            && previous[idx][removed + skipped].is_none()
+                        {
+                            previous[idx][removed + skipped] = Some(Some(prev_idx));
+                        }
+                    }
+                }
+            }
+
+            let (mut idx, mut removed) = (0..record.len())
+                .flat_map(|idx| (0..=k).map(move |removed| (idx, removed)))
+                .filter(|&(idx, removed)| {
+                    previous[idx][removed].is_some() && removed + record.len() - 1 - idx <= k
+                })
+                .min_by_key(|&(idx, removed)| removed + record.len() - 1 - idx)?;
+
+            let mut removed_levels = (idx + 1..record.len()).collect::<Vec<_>>();
+
+            while let Some(prev_idx) = previous[idx][removed].expect("reachable level") {
+                removed_levels.extend(prev_idx + 1..idx);
+                removed -= idx - prev_idx - 1;
+                idx = prev_idx;
+            }
+
+            removed_levels.extend(0..idx);
+            removed_levels.sort_unstable();
+
+            Some(removed_levels)
+        })
+        .min_by_key(|removed_levels| removed_levels.len())
+}
+
+#[derive(Debug)]
+enum Violation {
+    NoLevels,
+    StepTooSmall { pair_idx: usize, step: isize },
+    StepTooLarge { pair_idx: usize, step: isize },
+    DirectionChange { pair_idx: usize },
+}
+
+/// why a report is unsafe and which levels the dampener would remove to fix it
+#[derive(Debug)]
+struct Diagnosis {
+    violation: Violation,
+    removed_levels: Option<Vec<usize>>,
+}
+
+fn diagnose(record: &[isize], config: &Config) -> Option<Diagnosis> {
+    if record.is_empty() {
+        return Some(Diagnosis {
+            violation: Violation::NoLevels,
+            removed_levels: None,
+        });
+    }
+
+    let direction = record
+        .windows(2)
+        .map(|window| (window[1] - window[0]).signum())
+        .find(|sign| *sign != 0)
+        .unwrap_or(1);
+
+    let violation = record
+        .windows(2)
+        .enumerate()
+        .find_map(|(pair_idx, window)| {
+            let diff = window[1] - window[0];
+            let step = diff.abs();
+
+            if step > config.max_step {
+                Some(Violation::StepTooLarge { pair_idx, step })
+            } else if !is_allowed_step_size(diff, config) {
+                Some(Violation::StepTooSmall { pair_idx, step })
+            } else if diff.signum() == -direction || (diff == 0 && config.strict) {
+                Some(Violation::DirectionChange { pair_idx })
+            } else {
+                None
+            }
+        })?;
+
+    Some(Diagnosis {
+        violation,
+        removed_levels: dampen(record, config.tolerance, config),
+    })
+}
+
+fn print_diagnosis(record_idx: usize, record: &[isize], diagnosis: &Diagnosis, config: &Config) {
+    let (pair_idx, reason) = match diagnosis.violation {
+        Violation::NoLevels => {
+            println!("Report {}: has no levels", record_idx + 1);
+            return;
+        }
+        Violation::StepTooSmall { pair_idx, step } => {
+            (pair_idx, format!("step of {step} is too small"))
+        }
+        Violation::StepTooLarge { pair_idx, step } => {
+            (pair_idx, format!("step of {step} is too large"))
+        }
+        Violation::DirectionChange { pair_idx } => (pair_idx, "changes direction".to_string()),
+    };
+
+    let from = record[pair_idx];
+    let to = record[pair_idx + 1];
+
+    let fix = match &diagnosis.removed_levels {
+        Some(removed_levels) => {
+            let levels = removed_levels
+                .iter()
+                .map(|level_idx| format!("{} ({})", level_idx + 1, record[*level_idx]))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            if removed_levels.len() == 1 {
+                format!("dampener removes level {levels}")
+            } else {
+                format!("dampener removes levels {levels}")
+            }
+        }
+        None if config.tolerance == 1 => "no single level removal helps".to_string(),
+        None => format!("no removal of up to {} levels helps", config.tolerance),
+    };
+
+    println!(
+        "Report {}: levels {} and {} ({from} -> {to}) {reason}; {fix}",
+        record_idx + 1,
+        pair_idx + 1,
+        pair_idx + 2,
+    );
 }
 
 fn main() -> Result<(), Error> {
+    let config = config::load(Config::set)?;
+    config.check_steps()?;
+
     let input = File::open("input.txt")?;
     let reader = BufReader::new(input);
     let mut lines = reader.lines();
@@ -40,15 +290,18 @@ fn main() -> Result<(), Error> {
         );
     }
 
+    if std::env::args().any(|arg| arg == "--diagnose") {
+        for (record_idx, record) in records.iter().enumerate() {
+            if let Some(diagnosis) = diagnose(record, &config) {
+                print_diagnosis(record_idx, record, &diagnosis, &config);
+            }
+        }
+    }
+
     let valid_record_count = records
         .iter()
         .filter(|record| {
-            is_valid_record(record)
-                || (0..record.len()).any(|remove_idx| {
-                    let mut new_record = (*record).clone();
-                    new_record.remove(remove_idx);
-                    is_valid_record(&new_record)
-                })
+            is_valid_record(record, &config) || dampen(record, config.tolerance, &config).is_some()
         })
         .count();
 
@@ -56,3 +309,101 @@ fn main() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(strict: bool) -> Config {
+        Config {
+            strict,
+            ..Config::default()
+        }
+    }
+
+    /// xorshift, enough to spread small reports without pulling in a dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    /// tries every way of removing up to `k` levels
+    fn brute_force_is_safe(record: &[isize], k: usize, config: &Config) -> bool {
+        (0..1usize << record.len())
+            .filter(|mask| mask.count_ones() as usize <= k)
+            .any(|mask| {
+                let kept = record
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| mask & (1 << idx) == 0)
+                    .map(|(_, level)| *level)
+                    .collect::<Vec<_>>();
+
+                is_valid_record(&kept, config)
+            })
+    }
+
+    #[test]
+    fn empty_report_is_unsafe() {
+        assert!(!is_valid_record(&[], &config(true)));
+        assert!(!is_valid_record(&[], &config(false)));
+    }
+
+    #[test]
+    fn single_level_is_safe() {
+        assert!(is_valid_record(&[5], &config(true)));
+        assert!(is_valid_record(&[5], &config(false)));
+    }
+
+    #[test]
+    fn two_levels_depend_on_step() {
+        assert!(is_valid_record(&[5, 8], &config(true)));
+        assert!(is_valid_record(&[8, 5], &config(true)));
+        assert!(!is_valid_record(&[5, 9], &config(true)));
+        assert!(!is_valid_record(&[5, 5], &config(true)));
+        assert!(is_valid_record(&[5, 5], &config(false)));
+    }
+
+    #[test]
+    fn dampen_matches_brute_force() {
+        let mut rng = Rng(0x2024_1202);
+
+        for strict in [true, false] {
+            let config = config(strict);
+
+            for _ in 0..2000 {
+                let record = (0..rng.next(8))
+                    .map(|_| rng.next(10) as isize)
+                    .collect::<Vec<_>>();
+                let k = rng.next(4);
+
+                let removed_levels = dampen(&record, k, &config);
+
+                assert_eq!(
+                    is_valid_record(&record, &config) || removed_levels.is_some(),
+                    brute_force_is_safe(&record, k, &config),
+                    "record {record:?} with k = {k}, strict = {strict}"
+                );
+
+                if let Some(removed_levels) = removed_levels {
+                    let kept = record
+                        .iter()
+                        .enumerate()
+                        .filter(|(idx, _)| !removed_levels.contains(idx))
+                        .map(|(_, level)| *level)
+                        .collect::<Vec<_>>();
+
+                    assert!(removed_levels.len() <= k);
+                    assert!(is_valid_record(&kept, &config), "record {record:?}");
+                }
+            }
+        }
+    }
+}
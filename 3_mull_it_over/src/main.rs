@@ -1,117 +1,616 @@
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Error},
-    iter::Peekable,
+    io::{Error, ErrorKind, Read},
 };
 
-macro_rules! assert_next_items {
-    ($char_iter:expr, $($val:expr),*) => {
-        $(
-            $char_iter.next_if(|n| **n == $val)?;
-        )*
-    };
+/// what an instruction does once it has been recognized in the corrupted memory
+#[derive(Clone, Copy)]
+enum Action {
+    /// adds the result to the sum, as long as instructions are enabled. `None` means it overflowed
+    Accumulate(fn(&[isize]) -> Option<isize>),
+    Enable,
+    Disable,
 }
 
-fn parse_digits<'a, I>(iter: &mut Peekable<I>) -> Option<usize>
-where
-    I: Iterator<Item = &'a char>,
-{
-    let mut digits: Vec<char> = vec![];
-    while let Some(n) = iter.peek() {
-        if n.is_ascii_digit() {
-            digits.push(**n);
-            iter.next();
-        } else {
-            break;
-        }
+/// a single entry in the instruction registry, written as `name(arg,arg,...)` in memory
+#[derive(Clone, Copy)]
+struct InstructionSpec {
+    name: &'static str,
+    arg_count: usize,
+    action: Action,
+}
+
+static ALL_INSTRUCTIONS: &[InstructionSpec] = &[
+    InstructionSpec {
+        name: "mul",
+        arg_count: 2,
+        action: Action::Accumulate(|args| args[0].checked_mul(args[1])),
+    },
+    InstructionSpec {
+        name: "add",
+        arg_count: 2,
+        action: Action::Accumulate(|args| args[0].checked_add(args[1])),
+    },
+    InstructionSpec {
+        name: "sub",
+        arg_count: 2,
+        action: Action::Accumulate(|args| args[0].checked_sub(args[1])),
+    },
+    InstructionSpec {
+        name: "do",
+        arg_count: 0,
+        action: Action::Enable,
+    },
+    InstructionSpec {
+        name: "don't",
+        arg_count: 0,
+        action: Action::Disable,
+    },
+    InstructionSpec {
+        name: "on",
+        arg_count: 0,
+        action: Action::Enable,
+    },
+    InstructionSpec {
+        name: "off",
+        arg_count: 0,
+        action: Action::Disable,
+    },
+];
+
+/// the instructions the puzzle itself knows about
+static DEFAULT_INSTRUCTIONS: &[&str] = &["mul", "do", "don't"];
+
+/// the real puzzle only accepts numbers with one to three digits
+const DEFAULT_MAX_DIGITS: usize = 3;
+
+struct Token<'a> {
+    instruction: &'a InstructionSpec,
+    args: Vec<isize>,
+    /// byte offset of the instruction name in the memory dump
+    offset: usize,
+}
+
+/// an instruction name that wasn't followed by a valid argument list
+struct NearMiss<'a> {
+    instruction: &'a InstructionSpec,
+    offset: usize,
+    /// the bytes from the instruction name up to and including the one that made it invalid
+    text: String,
+    reason: String,
+}
+
+struct Rejection {
+    offset: usize,
+    reason: String,
+}
+
+enum Scanned<'a> {
+    Instruction(Token<'a>),
+    NearMiss(NearMiss<'a>),
+}
+
+/// everything the scanner found, kept around for the audit
+#[derive(Default)]
+struct Scan<'a> {
+    /// each instruction with whether instructions were enabled when it was reached
+    tokens: Vec<(Token<'a>, bool)>,
+    near_misses: Vec<NearMiss<'a>>,
+}
+
+fn describe_byte(byte: Option<&u8>) -> String {
+    match byte {
+        Some(byte) => format!("{:?}", *byte as char),
+        None => "end of input".to_string(),
     }
+}
 
-    if digits.is_empty() {
-        None
-    } else {
-        let digit_str: String = digits.iter().collect();
-        Some(digit_str.parse().expect("valid number"))
+fn expect_byte(bytes: &[u8], offset: usize, expected: u8) -> Result<(), Rejection> {
+    match bytes.get(offset) {
+        Some(byte) if *byte == expected => Ok(()),
+        byte => Err(Rejection {
+            offset,
+            reason: format!(
+                "expected {:?}, found {}",
+                expected as char,
+                describe_byte(byte)
+            ),
+        }),
     }
 }
 
-fn parse_potential_mul<'a, I>(char_iter: &mut Peekable<I>) -> Option<usize>
-where
-    I: Iterator<Item = &'a char>,
-{
-    assert_next_items!(char_iter, 'u', 'l', '(');
+struct Interpreter {
+    instructions: Vec<InstructionSpec>,
+    max_digits: usize,
+}
+
+impl Interpreter {
+    fn new(max_digits: usize) -> Self {
+        Self {
+            instructions: vec![],
+            max_digits,
+        }
+    }
+
+    fn register(&mut self, instruction: InstructionSpec) {
+        self.instructions.push(instruction);
+    }
+
+    /// the most bytes a single instruction, valid or not, can span, including the extra
+    /// digit needed to notice that a number is too long
+    fn max_instruction_len(&self) -> usize {
+        self.instructions
+            .iter()
+            .map(|instruction| {
+                let arg_len = self.max_digits.saturating_add(2);
+                instruction
+                    .name
+                    .len()
+                    .saturating_add(instruction.arg_count.saturating_mul(arg_len))
+                    .saturating_add(2)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn scan<R: Read>(&self, reader: R) -> Scanner<'_, R> {
+        Scanner {
+            interpreter: self,
+            reader,
+            buffer: vec![],
+            base: 0,
+            cursor: 0,
+            lookahead: self.max_instruction_len(),
+            eof: false,
+        }
+    }
+
+    /// tries every registered instruction at `offset` and keeps the longest match.
+    /// if none match, the attempt that got the furthest is returned as a near-miss
+    fn parse_instruction(
+        &self,
+        bytes: &[u8],
+        offset: usize,
+    ) -> Option<Result<(Token<'_>, usize), (NearMiss<'_>, usize)>> {
+        let attempts: Vec<_> = self
+            .instructions
+            .iter()
+            .filter(|instruction| bytes[offset..].starts_with(instruction.name.as_bytes()))
+            .map(|instruction| {
+                let args_offset = offset + instruction.name.len();
+
+                self.parse_arguments(bytes, args_offset, instruction.arg_count)
+                    .map(|(args, end)| {
+                        let token = Token {
+                            instruction,
+                            args,
+                            offset,
+                        };
+                        (token, end)
+                    })
+                    .map_err(|rejection| {
+                        let end = (rejection.offset + 1).min(bytes.len());
+                        let near_miss = NearMiss {
+                            instruction,
+                            offset,
+                            text: String::from_utf8_lossy(&bytes[offset..end]).to_string(),
+                            reason: rejection.reason,
+                        };
+                        (near_miss, end)
+                    })
+            })
+            .collect();
 
-    let left = parse_digits(char_iter)?;
+        let mut best: Option<Result<(Token, usize), (NearMiss, usize)>> = None;
 
-    assert_next_items!(char_iter, ',');
+        for attempt in attempts {
+            let is_better = match (&best, &attempt) {
+                (None, _) => true,
+                (Some(Err(_)), Ok(_)) => true,
+                (Some(Ok((_, best_end))), Ok((_, end))) => end > best_end,
+                (Some(Err((_, best_end))), Err((_, end))) => end > best_end,
+                (Some(Ok(_)), Err(_)) => false,
+            };
 
-    let right = parse_digits(char_iter)?;
+            if is_better {
+                best = Some(attempt);
+            }
+        }
+
+        best
+    }
+
+    /// parses `(arg,arg,...)` and returns the arguments with the offset right after the `)`
+    fn parse_arguments(
+        &self,
+        bytes: &[u8],
+        mut offset: usize,
+        arg_count: usize,
+    ) -> Result<(Vec<isize>, usize), Rejection> {
+        expect_byte(bytes, offset, b'(')?;
+        offset += 1;
+
+        let mut args = vec![];
+
+        for arg_idx in 0..arg_count {
+            if arg_idx > 0 {
+                expect_byte(bytes, offset, b',')?;
+                offset += 1;
+            }
 
-    assert_next_items!(char_iter, ')');
+            let digit_count = bytes[offset..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
 
-    Some(left * right)
+            if digit_count == 0 {
+                return Err(Rejection {
+                    offset,
+                    reason: format!(
+                        "expected a number, found {}",
+                        describe_byte(bytes.get(offset))
+                    ),
+                });
+            }
+
+            if digit_count > self.max_digits {
+                return Err(Rejection {
+                    offset: offset + self.max_digits,
+                    reason: format!("number has more than {} digits", self.max_digits),
+                });
+            }
+
+            let digits =
+                std::str::from_utf8(&bytes[offset..offset + digit_count]).expect("ascii digits");
+            args.push(digits.parse().map_err(|_| Rejection {
+                offset,
+                reason: format!("number {digits} is too large"),
+            })?);
+            offset += digit_count;
+        }
+
+        expect_byte(bytes, offset, b')')?;
+
+        Ok((args, offset + 1))
+    }
 }
 
-fn parse_potential_instr<'a, I>(char_iter: &mut Peekable<I>) -> Option<bool>
-where
-    I: Iterator<Item = &'a char>,
-{
-    assert_next_items!(char_iter, 'o');
+/// number of bytes already scanned before the buffer is compacted
+const COMPACT_THRESHOLD: usize = 64 * 1024;
 
-    match char_iter.peek() {
-        Some('(') => {
-            char_iter.next();
-            assert_next_items!(char_iter, ')');
-            Some(true)
+/// scans a memory dump of any size, only keeping the bytes around the current position
+struct Scanner<'a, R> {
+    interpreter: &'a Interpreter,
+    reader: R,
+    buffer: Vec<u8>,
+    /// offset of `buffer[0]` in the whole memory dump
+    base: usize,
+    cursor: usize,
+    lookahead: usize,
+    eof: bool,
+}
+
+impl<R: Read> Scanner<'_, R> {
+    /// makes sure a whole instruction starting at the cursor is in the buffer
+    fn fill(&mut self) -> Result<(), Error> {
+        if self.cursor >= COMPACT_THRESHOLD {
+            self.buffer.drain(..self.cursor);
+            self.base += self.cursor;
+            self.cursor = 0;
         }
-        Some('n') => {
-            char_iter.next();
-            assert_next_items!(char_iter, '\'', 't', '(', ')');
-            Some(false)
+
+        let mut chunk = [0; 8192];
+
+        while !self.eof && self.buffer.len() < self.cursor.saturating_add(self.lookahead) {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
         }
-        _ => None,
+
+        Ok(())
     }
 }
 
-fn accumulate_muls(chars: &[char]) -> usize {
-    let mut char_iter = chars.iter().peekable();
+impl<'a, R: Read> Iterator for Scanner<'a, R> {
+    type Item = Result<Scanned<'a>, Error>;
 
-    let mut sum: usize = 0;
-    let mut last_instr: bool = true;
-    // not using regex cause we ballin
-    while let Some(c) = char_iter.next() {
-        match *c {
-            'm' => {
-                if let Some(result) = parse_potential_mul(&mut char_iter) {
-                    if last_instr {
-                        sum += result;
-                    }
-                }
+    fn next(&mut self) -> Option<Self::Item> {
+        // not using regex cause we ballin
+        loop {
+            if let Err(err) = self.fill() {
+                return Some(Err(err));
             }
-            'd' => {
-                if let Some(instr) = parse_potential_instr(&mut char_iter) {
-                    last_instr = instr;
+
+            if self.cursor >= self.buffer.len() {
+                return None;
+            }
+
+            match self
+                .interpreter
+                .parse_instruction(&self.buffer, self.cursor)
+            {
+                Some(Ok((mut token, end))) => {
+                    token.offset += self.base;
+                    self.cursor = end;
+                    return Some(Ok(Scanned::Instruction(token)));
                 }
+                Some(Err((mut near_miss, _))) => {
+                    near_miss.offset += self.base;
+                    self.cursor += 1;
+                    return Some(Ok(Scanned::NearMiss(near_miss)));
+                }
+                None => self.cursor += 1,
             }
-            _ => {
-                continue;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// honours the enable and disable instructions
+    Conditional,
+    /// sums every instruction, like part 1
+    Unconditional,
+}
+
+struct Machine {
+    mode: Mode,
+    enabled: bool,
+    sum: isize,
+}
+
+impl Machine {
+    fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            enabled: true,
+            sum: 0,
+        }
+    }
+
+    /// runs the instruction and returns whether instructions were enabled when it was reached,
+    /// failing if the result or the sum overflows
+    fn execute(&mut self, token: &Token) -> Result<bool, Error> {
+        let was_enabled = self.enabled;
+
+        match token.instruction.action {
+            Action::Accumulate(apply) => {
+                if self.enabled {
+                    self.sum = apply(&token.args)
+                        .and_then(|result| self.sum.checked_add(result))
+                        .ok_or_else(|| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!(
+                                    "{} at offset {} overflows the sum",
+                                    format_instruction(token),
+                                    token.offset
+                                ),
+                            )
+                        })?;
+                }
             }
+            Action::Enable => self.enabled = true,
+            Action::Disable => self.enabled = self.mode == Mode::Unconditional,
         }
+
+        Ok(was_enabled)
     }
+}
+
+fn format_instruction(token: &Token) -> String {
+    let args = token
+        .args
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
 
-    sum
+    format!("{}({args})", token.instruction.name)
+}
+
+fn print_audit(scan: &Scan) {
+    let mut lines: Vec<(usize, String)> = vec![];
+
+    for (token, enabled) in &scan.tokens {
+        let state = if *enabled { "enabled" } else { "disabled" };
+        lines.push((
+            token.offset,
+            format!(
+                "{:>8}  {:<16} {state}",
+                token.offset,
+                format_instruction(token)
+            ),
+        ));
+    }
+
+    for near_miss in &scan.near_misses {
+        lines.push((
+            near_miss.offset,
+            format!(
+                "{:>8}  {:<16} rejected {}: {}",
+                near_miss.offset,
+                format!("{:?}", near_miss.text),
+                near_miss.instruction.name,
+                near_miss.reason
+            ),
+        ));
+    }
+
+    lines.sort_by_key(|(offset, _)| *offset);
+
+    for (_, line) in lines {
+        println!("{line}");
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+fn print_audit_json(scan: &Scan, sum: isize) {
+    let instructions = scan
+        .tokens
+        .iter()
+        .map(|(token, enabled)| {
+            let args = token
+                .args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            format!(
+                "{{\"offset\":{},\"name\":{},\"args\":[{args}],\"enabled\":{enabled}}}",
+                token.offset,
+                json_string(token.instruction.name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let near_misses = scan
+        .near_misses
+        .iter()
+        .map(|near_miss| {
+            format!(
+                "{{\"offset\":{},\"name\":{},\"text\":{},\"reason\":{}}}",
+                near_miss.offset,
+                json_string(near_miss.instruction.name),
+                json_string(&near_miss.text),
+                json_string(&near_miss.reason)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    println!("{{\"sum\":{sum},\"instructions\":[{instructions}],\"near_misses\":[{near_misses}]}}");
+}
+
+fn build_interpreter() -> Result<Interpreter, Error> {
+    let max_digits = match std::env::args().find_map(|arg| {
+        arg.strip_prefix("--max-digits=")
+            .map(|max_digits| max_digits.parse::<usize>())
+    }) {
+        Some(Ok(max_digits)) => max_digits,
+        Some(Err(err)) => return Err(Error::new(ErrorKind::InvalidInput, err)),
+        None => DEFAULT_MAX_DIGITS,
+    };
+
+    let names = match std::env::args().find_map(|arg| {
+        arg.strip_prefix("--instructions=")
+            .map(|names| names.to_string())
+    }) {
+        Some(names) => names.split(',').map(|name| name.to_string()).collect(),
+        None => DEFAULT_INSTRUCTIONS
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>(),
+    };
+
+    let mut interpreter = Interpreter::new(max_digits);
+
+    for name in names {
+        let Some(instruction) = ALL_INSTRUCTIONS
+            .iter()
+            .find(|instruction| instruction.name == name)
+        else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown instruction {name}"),
+            ));
+        };
+
+        interpreter.register(*instruction);
+    }
+
+    Ok(interpreter)
+}
+
+enum AuditFormat {
+    Text,
+    Json,
+}
+
+fn parse_audit_format() -> Result<Option<AuditFormat>, Error> {
+    match std::env::args()
+        .find(|arg| arg.starts_with("--audit"))
+        .as_deref()
+    {
+        Some("--audit") | Some("--audit=text") => Ok(Some(AuditFormat::Text)),
+        Some("--audit=json") => Ok(Some(AuditFormat::Json)),
+        Some(arg) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown audit format {arg}"),
+        )),
+        None => Ok(None),
+    }
+}
+
+fn parse_mode() -> Result<Mode, Error> {
+    match std::env::args()
+        .find_map(|arg| arg.strip_prefix("--mode=").map(|mode| mode.to_string()))
+        .as_deref()
+    {
+        Some("conditional") | None => Ok(Mode::Conditional),
+        Some("unconditional") => Ok(Mode::Unconditional),
+        Some(mode) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown mode {mode}"),
+        )),
+    }
 }
 
 fn main() -> Result<(), Error> {
+    let interpreter = build_interpreter()?;
+    let audit_format = parse_audit_format()?;
+    let mut machine = Machine::new(parse_mode()?);
+    let mut scan = Scan::default();
+
     let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
 
-    let chars: Vec<char> = reader
-        .lines()
-        .flat_map(|line| line.expect("valid line").chars().collect::<Vec<char>>())
-        .collect();
+    for scanned in interpreter.scan(file) {
+        match scanned? {
+            Scanned::Instruction(token) => {
+                let enabled = machine.execute(&token)?;
+
+                if audit_format.is_some() {
+                    scan.tokens.push((token, enabled));
+                }
+            }
+            Scanned::NearMiss(near_miss) => {
+                if audit_format.is_some() {
+                    scan.near_misses.push(near_miss);
+                }
+            }
+        }
+    }
+
+    match audit_format {
+        Some(AuditFormat::Text) => print_audit(&scan),
+        Some(AuditFormat::Json) => {
+            print_audit_json(&scan, machine.sum);
+            return Ok(());
+        }
+        None => {}
+    }
 
-    let sum = accumulate_muls(&chars);
-    println!("Sum is {sum}");
+    println!("Sum is {}", machine.sum);
 
     Ok(())
 }
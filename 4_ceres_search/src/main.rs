@@ -1,8 +1,10 @@
 use std::{
+    collections::{HashMap, VecDeque},
     fs::File,
     io::{BufRead, BufReader, Error},
 };
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Direction {
     Left,
     Right,
@@ -43,16 +45,22 @@ impl Direction {
 }
 
 static ALL_DIRS: &[Direction] = &[
-    // Direction::Right,
-    // Direction::Left,
-    // Direction::Up,
-    // Direction::Down,
+    Direction::Right,
+    Direction::Left,
+    Direction::Up,
+    Direction::Down,
     Direction::UpLeft,
     Direction::UpRight,
     Direction::DownLeft,
     Direction::DownRight,
 ];
 
+/// a small grid of letters to look for, `None` cells match any letter
+type Template = Vec<Vec<Option<char>>>;
+
+/// the X-MAS shape from part 2, `.` is a wildcard
+static XMAS_TEMPLATE: &[&str] = &["M.S", ".A.", "M.S"];
+
 fn apply_direction_to_position(
     direction: &Direction,
     position: (usize, usize),
@@ -65,39 +73,379 @@ fn apply_direction_to_position(
     Some((new_y, new_x))
 }
 
-fn get_char_in_dir(
+/// a word found in the grid, starting at `position` and read towards `direction`
+#[derive(Debug)]
+struct WordMatch {
+    position: (usize, usize),
+    direction: Direction,
+}
+
+fn chars_match(a: char, b: char, ignore_case: bool) -> bool {
+    if ignore_case {
+        a.to_lowercase().eq(b.to_lowercase())
+    } else {
+        a == b
+    }
+}
+
+fn is_word_at_pos(
     vec: &[Vec<char>],
+    word: &[char],
+    position: (usize, usize),
     direction: &Direction,
+    ignore_case: bool,
+) -> bool {
+    let mut pos = Some(position);
+
+    for expected in word {
+        let Some(current) = pos else {
+            return false;
+        };
+
+        let Some(c) = vec.get(current.0).and_then(|line| line.get(current.1)) else {
+            return false;
+        };
+
+        if !chars_match(*c, *expected, ignore_case) {
+            return false;
+        }
+
+        pos = apply_direction_to_position(direction, current);
+    }
+
+    true
+}
+
+fn find_word(
+    vec: &[Vec<char>],
+    word: &str,
+    directions: &[Direction],
+    ignore_case: bool,
+) -> Vec<WordMatch> {
+    let word: Vec<char> = word.chars().collect();
+    if word.is_empty() {
+        return vec![];
+    }
+
+    let mut matches = vec![];
+
+    for (line_idx, line) in vec.iter().enumerate() {
+        for char_idx in 0..line.len() {
+            for direction in directions {
+                if is_word_at_pos(vec, &word, (line_idx, char_idx), direction, ignore_case) {
+                    matches.push(WordMatch {
+                        position: (line_idx, char_idx),
+                        direction: direction.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    matches
+}
+
+/// rows shorter than the widest one are padded with wildcards
+fn parse_template(lines: &[String]) -> Template {
+    let width = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| {
+            let mut row: Vec<Option<char>> = line
+                .chars()
+                .map(|c| if c == '.' { None } else { Some(c) })
+                .collect();
+            row.resize(width, None);
+            row
+        })
+        .collect()
+}
+
+fn rotate_template(template: &Template) -> Template {
+    let height = template.len();
+    let width = template.first().map_or(0, |row| row.len());
+
+    (0..width)
+        .map(|y| (0..height).map(|x| template[height - 1 - x][y]).collect())
+        .collect()
+}
+
+fn reflect_template(template: &Template) -> Template {
+    template
+        .iter()
+        .map(|row| row.iter().rev().copied().collect())
+        .collect()
+}
+
+/// all 4 rotations of the template and of its mirror image, without duplicates
+fn get_template_variants(template: &Template) -> Vec<Template> {
+    let mut variants: Vec<Template> = vec![];
+
+    for start in [template.clone(), reflect_template(template)] {
+        let mut variant = start;
+
+        for _ in 0..4 {
+            if !variants.contains(&variant) {
+                variants.push(variant.clone());
+            }
+
+            variant = rotate_template(&variant);
+        }
+    }
+
+    variants
+}
+
+/// every template cell has to lie inside the grid, wildcard cells only skip the character check
+fn is_template_at_pos(
+    vec: &[Vec<char>],
+    template: &Template,
     position: (usize, usize),
-) -> Option<char> {
-    let pos = apply_direction_to_position(direction, position)?;
+    ignore_case: bool,
+) -> bool {
+    template.iter().enumerate().all(|(y, row)| {
+        row.iter().enumerate().all(|(x, expected)| {
+            vec.get(position.0 + y)
+                .and_then(|line| line.get(position.1 + x))
+                .is_some_and(|c| {
+                    expected.is_none_or(|expected| chars_match(*c, expected, ignore_case))
+                })
+        })
+    })
+}
 
-    let char = vec.get(pos.0).map(|line| line.get(pos.1))??;
+/// the top left corner of a template variant found in the grid
+#[derive(Debug)]
+struct TemplateMatch {
+    position: (usize, usize),
+    variant_idx: usize,
+}
 
-    Some(*char)
+fn find_template(vec: &[Vec<char>], template: &Template, ignore_case: bool) -> Vec<TemplateMatch> {
+    let variants = get_template_variants(template);
+    let mut matches = vec![];
+
+    for (line_idx, line) in vec.iter().enumerate() {
+        for char_idx in 0..line.len() {
+            for (variant_idx, variant) in variants.iter().enumerate() {
+                if is_template_at_pos(vec, variant, (line_idx, char_idx), ignore_case) {
+                    matches.push(TemplateMatch {
+                        position: (line_idx, char_idx),
+                        variant_idx,
+                    });
+                }
+            }
+        }
+    }
+
+    matches
 }
 
-fn is_valid_xmas_at_pos(vec: &[Vec<char>], line_idx: usize, char_idx: usize) -> bool {
-    let pos = (line_idx, char_idx);
-    if vec[line_idx][char_idx] != 'A' {
-        return false;
+fn fold_case(c: char, ignore_case: bool) -> char {
+    if ignore_case {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
     }
+}
 
-    let mut mas_count = 0;
+/// a word as it is stored in the automaton, reversed words find matches going backwards
+struct Pattern {
+    word_idx: usize,
+    len: usize,
+    reversed: bool,
+}
+
+/// Aho-Corasick automaton over a word list and the reversed words
+struct WordAutomaton {
+    transitions: Vec<HashMap<char, usize>>,
+    fail: Vec<usize>,
+    /// patterns ending in each state, including the ones reached through fail links
+    outputs: Vec<Vec<usize>>,
+    patterns: Vec<Pattern>,
+}
 
-    for dir in ALL_DIRS {
-        if get_char_in_dir(vec, dir, pos).map_or(true, |c| c != 'M') {
-            continue;
+impl WordAutomaton {
+    fn new(words: &[String], ignore_case: bool) -> Self {
+        let mut automaton = WordAutomaton {
+            transitions: vec![HashMap::new()],
+            fail: vec![0],
+            outputs: vec![vec![]],
+            patterns: vec![],
+        };
+
+        for (word_idx, word) in words.iter().enumerate() {
+            let chars: Vec<char> = word.chars().map(|c| fold_case(c, ignore_case)).collect();
+            if chars.is_empty() {
+                continue;
+            }
+
+            automaton.insert(chars.iter().copied(), word_idx, chars.len(), false);
+            automaton.insert(chars.iter().rev().copied(), word_idx, chars.len(), true);
         }
 
-        if get_char_in_dir(vec, &dir.get_opposite(), pos).map_or(true, |c| c != 'S') {
-            continue;
+        automaton.build_fail_links();
+        automaton
+    }
+
+    fn insert(
+        &mut self,
+        chars: impl Iterator<Item = char>,
+        word_idx: usize,
+        len: usize,
+        reversed: bool,
+    ) {
+        let mut state = 0;
+
+        for c in chars {
+            state = match self.transitions[state].get(&c) {
+                Some(next) => *next,
+                None => {
+                    self.transitions.push(HashMap::new());
+                    self.fail.push(0);
+                    self.outputs.push(vec![]);
+
+                    let next = self.transitions.len() - 1;
+                    self.transitions[state].insert(c, next);
+                    next
+                }
+            };
         }
 
-        mas_count += 1;
+        self.outputs[state].push(self.patterns.len());
+        self.patterns.push(Pattern {
+            word_idx,
+            len,
+            reversed,
+        });
     }
 
-    mas_count == 2
+    fn build_fail_links(&mut self) {
+        let mut queue: VecDeque<usize> = self.transitions[0].values().copied().collect();
+
+        while let Some(state) = queue.pop_front() {
+            let transitions: Vec<(char, usize)> = self.transitions[state]
+                .iter()
+                .map(|(c, next)| (*c, *next))
+                .collect();
+
+            for (c, next) in transitions {
+                let mut fallback = self.fail[state];
+                while fallback != 0 && !self.transitions[fallback].contains_key(&c) {
+                    fallback = self.fail[fallback];
+                }
+
+                let fail = self.transitions[fallback].get(&c).copied().unwrap_or(0);
+
+                self.fail[next] = fail;
+                let inherited = self.outputs[fail].clone();
+                self.outputs[next].extend(inherited);
+
+                queue.push_back(next);
+            }
+        }
+    }
+
+    fn step(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(next) = self.transitions[state].get(&c) {
+                return *next;
+            }
+
+            if state == 0 {
+                return 0;
+            }
+
+            state = self.fail[state];
+        }
+    }
+}
+
+/// every row, column and diagonal of the grid, as positions in reading order. rows can differ in
+/// length, a line is split wherever it crosses a missing cell so no word is read across the gap
+fn get_grid_lines(vec: &[Vec<char>]) -> Vec<(Direction, Vec<(usize, usize)>)> {
+    let height = vec.len();
+    let width = vec.iter().map(|line| line.len()).max().unwrap_or(0);
+
+    let mut starts: Vec<(Direction, (usize, usize))> = vec![];
+    for y in 0..height {
+        starts.push((Direction::Right, (y, 0)));
+        starts.push((Direction::DownRight, (y, 0)));
+        if y > 0 {
+            starts.push((Direction::DownLeft, (y, width.saturating_sub(1))));
+        }
+    }
+    for x in 0..width {
+        starts.push((Direction::Down, (0, x)));
+        starts.push((Direction::DownLeft, (0, x)));
+        if x > 0 {
+            starts.push((Direction::DownRight, (0, x)));
+        }
+    }
+
+    starts
+        .into_iter()
+        .flat_map(|(direction, start)| {
+            let mut lines = vec![];
+            let mut positions = vec![];
+            let mut pos = Some(start);
+
+            while let Some(current) = pos.filter(|(y, x)| *y < height && *x < width) {
+                if vec[current.0].get(current.1).is_some() {
+                    positions.push(current);
+                } else if !positions.is_empty() {
+                    lines.push((direction.clone(), std::mem::take(&mut positions)));
+                }
+
+                pos = apply_direction_to_position(&direction, current);
+            }
+
+            if !positions.is_empty() {
+                lines.push((direction, positions));
+            }
+
+            lines
+        })
+        .collect()
+}
+
+/// finds all words in all 8 directions, scanning every grid line once. returns the matches per word
+fn find_words(vec: &[Vec<char>], words: &[String], ignore_case: bool) -> Vec<Vec<WordMatch>> {
+    let automaton = WordAutomaton::new(words, ignore_case);
+    let mut matches: Vec<Vec<WordMatch>> = words.iter().map(|_| vec![]).collect();
+
+    for (direction, positions) in get_grid_lines(vec) {
+        let mut state = 0;
+
+        for (idx, pos) in positions.iter().enumerate() {
+            state = automaton.step(state, fold_case(vec[pos.0][pos.1], ignore_case));
+
+            for pattern_idx in &automaton.outputs[state] {
+                let pattern = &automaton.patterns[*pattern_idx];
+
+                let word_match = if pattern.reversed {
+                    WordMatch {
+                        position: *pos,
+                        direction: direction.get_opposite(),
+                    }
+                } else {
+                    WordMatch {
+                        position: positions[idx + 1 - pattern.len],
+                        direction: direction.clone(),
+                    }
+                };
+
+                matches[pattern.word_idx].push(word_match);
+            }
+        }
+    }
+
+    matches
 }
 
 fn main() -> Result<(), Error> {
@@ -109,16 +457,142 @@ fn main() -> Result<(), Error> {
         .map(|line| line.expect("valid line").chars().collect())
         .collect();
 
-    let mut sum = 0;
-    for (line_idx, line) in chars.iter().enumerate() {
-        for (char_idx, _) in line.iter().enumerate() {
-            if is_valid_xmas_at_pos(&chars, line_idx, char_idx) {
-                sum += 1;
+    let ignore_case = std::env::args().any(|arg| arg == "--ignore-case");
+
+    let xmas_matches = find_word(&chars, "XMAS", ALL_DIRS, ignore_case);
+
+    if std::env::args().any(|arg| arg == "--list") {
+        for word_match in &xmas_matches {
+            println!(
+                "XMAS at {},{} going {:?}",
+                word_match.position.0, word_match.position.1, word_match.direction
+            );
+        }
+    }
+
+    println!("XMAS count is {}", xmas_matches.len());
+
+    if let Some(path) =
+        std::env::args().find_map(|arg| arg.strip_prefix("--words=").map(|path| path.to_string()))
+    {
+        let words: Vec<String> = BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|word| word.trim().to_string())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let word_matches = find_words(&chars, &words, ignore_case);
+
+        for (word, matches) in words.iter().zip(&word_matches) {
+            println!("{word}: {} hits", matches.len());
+
+            if std::env::args().any(|arg| arg == "--list") {
+                for word_match in matches {
+                    println!(
+                        "  at {},{} going {:?}",
+                        word_match.position.0, word_match.position.1, word_match.direction
+                    );
+                }
             }
         }
     }
 
-    print!("Sum is {sum}");
+    let template_lines: Vec<String> = match std::env::args()
+        .find_map(|arg| arg.strip_prefix("--template=").map(|path| path.to_string()))
+    {
+        Some(path) => BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<Result<_, _>>()?,
+        None => XMAS_TEMPLATE.iter().map(|line| line.to_string()).collect(),
+    };
+    let template = parse_template(&template_lines);
+
+    let template_matches = find_template(&chars, &template, ignore_case);
+
+    if std::env::args().any(|arg| arg == "--list") {
+        for template_match in &template_matches {
+            println!(
+                "Template variant {} at {},{}",
+                template_match.variant_idx, template_match.position.0, template_match.position.1
+            );
+        }
+    }
+
+    println!("Sum is {}", template_matches.len());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &[&str] = &[
+        "MMMSXXMASM",
+        "MSAMXMSMSA",
+        "AMXSXMAAMM",
+        "MSAMASMSMX",
+        "XMASAMXAMM",
+        "XXAMMXXAMA",
+        "SMSMSASXSS",
+        "SAXAMASAAA",
+        "MAMMMXMMMM",
+        "MXMXAXMASX",
+    ];
+
+    fn grid(lines: &[&str]) -> Vec<Vec<char>> {
+        lines.iter().map(|line| line.chars().collect()).collect()
+    }
+
+    fn assert_same_as_find_word(vec: &[Vec<char>], words: &[&str]) {
+        let words = words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>();
+        let matches = find_words(vec, &words, false);
+
+        for (word, word_matches) in words.iter().zip(&matches) {
+            let mut found = word_matches
+                .iter()
+                .map(|word_match| format!("{:?} {:?}", word_match.position, word_match.direction))
+                .collect::<Vec<_>>();
+            let mut expected = find_word(vec, word, ALL_DIRS, false)
+                .iter()
+                .map(|word_match| format!("{:?} {:?}", word_match.position, word_match.direction))
+                .collect::<Vec<_>>();
+
+            found.sort();
+            expected.sort();
+            assert_eq!(found, expected, "matches of {word}");
+        }
+    }
+
+    #[test]
+    fn example_finds_every_xmas() {
+        let vec = grid(EXAMPLE);
+        let matches = find_words(&vec, &["XMAS".to_string()], false);
+
+        assert_eq!(matches[0].len(), 18);
+        assert_same_as_find_word(&vec, &["XMAS", "MAS", "SAM", "AXA"]);
+    }
+
+    #[test]
+    fn trailing_blank_row_is_skipped() {
+        let mut lines = EXAMPLE.to_vec();
+        lines.push("");
+        let vec = grid(&lines);
+
+        let matches = find_words(&vec, &["XMAS".to_string()], false);
+
+        assert_eq!(matches[0].len(), 18);
+    }
+
+    #[test]
+    fn words_do_not_cross_short_rows() {
+        let vec = grid(&["XMAS", "MM", "", "SAMX", "XMASXMAS"]);
+
+        assert_same_as_find_word(&vec, &["XMAS", "XM", "MAS", "SX", "MM"]);
+    }
+}
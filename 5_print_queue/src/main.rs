@@ -1,8 +1,8 @@
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
 };
 
 /// specifies which numbers must come after the key
@@ -25,34 +25,454 @@ fn get_rules(rules: &[String]) -> Rules {
     map
 }
 
+/// a rule `before|after` broken by an update, `after` shows up at `after_idx` ahead of `before`
+struct Violation {
+    before: usize,
+    after: usize,
+    before_idx: usize,
+    after_idx: usize,
+}
+
+fn get_violations(update: &[usize], rules: &Rules) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    for (before_idx, before) in update.iter().enumerate() {
+        for (after_idx, after) in update[..before_idx].iter().enumerate() {
+            if rules
+                .get(before)
+                .is_some_and(|nums_after| nums_after.contains(after))
+            {
+                violations.push(Violation {
+                    before: *before,
+                    after: *after,
+                    before_idx,
+                    after_idx,
+                });
+            }
+        }
+    }
+
+    violations
+}
+
 fn is_valid_update(update: &[usize], rules: &Rules) -> bool {
-    for (idx, entry) in update.iter().enumerate() {
-        if update[..idx].iter().any(|num| {
-            rules
-                .get(entry)
-                .is_some_and(|nums_after| nums_after.contains(num))
-        }) {
-            return false;
+    get_violations(update, rules).is_empty()
+}
+
+/// pages of an update whose rules contradict each other, each one has to come before the next
+/// and the last one before the first
+struct Cycle {
+    pages: Vec<usize>,
+}
+
+/// the rules restricted to a single update, `graph[idx]` holds the indices that must come after `idx`
+fn get_update_graph(update: &[usize], rules: &Rules) -> Vec<Vec<usize>> {
+    update
+        .iter()
+        .enumerate()
+        .map(|(idx, page)| {
+            update
+                .iter()
+                .enumerate()
+                .filter(|(other_idx, other)| {
+                    *other_idx != idx
+                        && rules
+                            .get(page)
+                            .is_some_and(|nums_after| nums_after.contains(other))
+                })
+                .map(|(other_idx, _)| other_idx)
+                .collect()
+        })
+        .collect()
+}
+
+/// every remaining index still has a remaining predecessor, so walking backwards has to loop
+fn find_cycle(graph: &[Vec<usize>], remaining: &[bool]) -> Vec<usize> {
+    let predecessor = |idx: usize| {
+        (0..graph.len())
+            .find(|prev_idx| remaining[*prev_idx] && graph[*prev_idx].contains(&idx))
+            .expect("remaining index has a remaining predecessor")
+    };
+
+    let mut idx = remaining
+        .iter()
+        .position(|is_remaining| *is_remaining)
+        .expect("some index is remaining");
+    let mut path = vec![];
+
+    while !path.contains(&idx) {
+        path.push(idx);
+        idx = predecessor(idx);
+    }
+
+    let start = path
+        .iter()
+        .position(|path_idx| *path_idx == idx)
+        .expect("in path");
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
+    cycle
+}
+
+/// topologically sorts the indices of the update, keeping their original order where the graph
+/// allows it
+fn topological_sort(update: &[usize], graph: &[Vec<usize>]) -> Result<Vec<usize>, Cycle> {
+    let mut in_degree = vec![0; update.len()];
+    for next_indices in graph {
+        for next_idx in next_indices {
+            in_degree[*next_idx] += 1;
+        }
+    }
+
+    let mut available: BinaryHeap<Reverse<usize>> = (0..update.len())
+        .filter(|idx| in_degree[*idx] == 0)
+        .map(Reverse)
+        .collect();
+    let mut remaining = vec![true; update.len()];
+    let mut fixed = vec![];
+
+    while let Some(Reverse(idx)) = available.pop() {
+        remaining[idx] = false;
+        fixed.push(idx);
+
+        for next_idx in &graph[idx] {
+            in_degree[*next_idx] -= 1;
+
+            if in_degree[*next_idx] == 0 {
+                available.push(Reverse(*next_idx));
+            }
+        }
+    }
+
+    if fixed.len() < update.len() {
+        let pages = find_cycle(graph, &remaining)
+            .into_iter()
+            .map(|idx| update[idx])
+            .collect();
+
+        return Err(Cycle { pages });
+    }
+
+    Ok(fixed)
+}
+
+fn fix_invalid_update(update: &[usize], rules: &Rules) -> Result<Vec<usize>, Cycle> {
+    let order = topological_sort(update, &get_update_graph(update, rules))?;
+
+    Ok(order.into_iter().map(|idx| update[idx]).collect())
+}
+
+/// `closure[a][b]` tells if the rules force index `a` before index `b`, directly or through other pages
+fn get_transitive_closure(graph: &[Vec<usize>]) -> Vec<Vec<bool>> {
+    let mut closure = vec![vec![false; graph.len()]; graph.len()];
+
+    for (idx, next_indices) in graph.iter().enumerate() {
+        for next_idx in next_indices {
+            closure[idx][*next_idx] = true;
+        }
+    }
+
+    for via in 0..graph.len() {
+        let via_row = closure[via].clone();
+
+        for from_row in closure.iter_mut() {
+            if !from_row[via] {
+                continue;
+            }
+
+            for (is_after, is_after_via) in from_row.iter_mut().zip(&via_row) {
+                *is_after |= *is_after_via;
+            }
+        }
+    }
+
+    closure
+}
+
+fn find_augmenting_path(
+    edges: &[Vec<usize>],
+    left: usize,
+    visited: &mut [bool],
+    matched_left: &mut [Option<usize>],
+) -> bool {
+    for right in &edges[left] {
+        if visited[*right] {
+            continue;
+        }
+        visited[*right] = true;
+
+        if matched_left[*right]
+            .is_none_or(|other| find_augmenting_path(edges, other, visited, matched_left))
+        {
+            matched_left[*right] = Some(left);
+            return true;
         }
     }
 
-    true
+    false
 }
 
-fn fix_invalid_update(update: &[usize], rules: &Rules) -> Vec<usize> {
-    let mut update = update.to_vec();
+/// the largest set of indices that can stay where they are relative to each other.
+/// two pages conflict when the earlier one has to come after the later one, which makes the
+/// conflicts a partial order, so by Dilworth's theorem its largest antichain is found through a
+/// maximum bipartite matching and König's theorem
+fn get_pages_to_keep(closure: &[Vec<bool>]) -> Vec<usize> {
+    let len = closure.len();
+
+    let edges: Vec<Vec<usize>> = (0..len)
+        .map(|left| {
+            (left + 1..len)
+                .filter(|right| closure[*right][left])
+                .collect()
+        })
+        .collect();
+
+    let mut matched_left: Vec<Option<usize>> = vec![None; len];
+    for left in 0..len {
+        find_augmenting_path(&edges, left, &mut vec![false; len], &mut matched_left);
+    }
 
-    update.sort_by(|a, b| {
-        let nums_after_a = rules.get(a);
+    let mut is_left_matched = vec![false; len];
+    for left in matched_left.iter().flatten() {
+        is_left_matched[*left] = true;
+    }
+
+    // alternating paths from unmatched left vertices give the minimum vertex cover
+    let mut reached_left = vec![false; len];
+    let mut reached_right = vec![false; len];
+    let mut stack: Vec<usize> = (0..len).filter(|left| !is_left_matched[*left]).collect();
 
-        if nums_after_a.is_some_and(|after| after.contains(b)) {
-            return Ordering::Less;
+    for left in &stack {
+        reached_left[*left] = true;
+    }
+
+    while let Some(left) = stack.pop() {
+        for right in &edges[left] {
+            if reached_right[*right] {
+                continue;
+            }
+            reached_right[*right] = true;
+
+            if let Some(next_left) = matched_left[*right] {
+                if !reached_left[next_left] {
+                    reached_left[next_left] = true;
+                    stack.push(next_left);
+                }
+            }
+        }
+    }
+
+    (0..len)
+        .filter(|idx| reached_left[*idx] && !reached_right[*idx])
+        .collect()
+}
+
+/// a page taken out at `from_idx` and put back at `to_idx`. both refer to the update as it is
+/// right before the move, so applying the moves in order turns it into the fixed update
+struct Move {
+    page: usize,
+    from_idx: usize,
+    to_idx: usize,
+}
+
+/// the fewest pages to move to fix the update, along with the update they fix it into
+fn get_minimal_moves(update: &[usize], rules: &Rules) -> Result<(Vec<usize>, Vec<Move>), Cycle> {
+    let mut graph = get_update_graph(update, rules);
+
+    // fails on a cycle before the closure gets to loop around it
+    topological_sort(update, &graph)?;
+
+    let kept = get_pages_to_keep(&get_transitive_closure(&graph));
+
+    for pair in kept.windows(2) {
+        graph[pair[0]].push(pair[1]);
+    }
+
+    let order = topological_sort(update, &graph)?;
+
+    // every moved page goes right behind the page before it in the fixed update. that page is
+    // kept or was moved earlier, so it is already in its final place relative to the others
+    let mut current = (0..update.len()).collect::<Vec<_>>();
+    let mut moves = vec![];
+
+    for (order_idx, idx) in order.iter().enumerate() {
+        if kept.contains(idx) {
+            continue;
+        }
+
+        let from_idx = current
+            .iter()
+            .position(|current_idx| current_idx == idx)
+            .expect("page is in the update");
+        current.remove(from_idx);
+
+        let to_idx = match order_idx.checked_sub(1) {
+            Some(prev_order_idx) => {
+                current
+                    .iter()
+                    .position(|current_idx| *current_idx == order[prev_order_idx])
+                    .expect("page is in the update")
+                    + 1
+            }
+            None => 0,
         };
+        current.insert(to_idx, *idx);
 
-        Ordering::Greater
-    });
+        if from_idx != to_idx {
+            moves.push(Move {
+                page: update[*idx],
+                from_idx,
+                to_idx,
+            });
+        }
+    }
 
-    update
+    debug_assert_eq!(current, order);
+
+    Ok((order.into_iter().map(|idx| update[idx]).collect(), moves))
+}
+
+fn print_explanation(update_idx: usize, update: &[usize], rules: &Rules) {
+    println!("Update {} is invalid:", update_idx + 1);
+
+    for violation in get_violations(update, rules) {
+        println!(
+            "  rule {}|{} broken: {} at position {} comes before {} at position {}",
+            violation.before,
+            violation.after,
+            violation.after,
+            violation.after_idx + 1,
+            violation.before,
+            violation.before_idx + 1
+        );
+    }
+
+    match get_minimal_moves(update, rules) {
+        Ok((fixed, moves)) => {
+            for page_move in moves {
+                println!(
+                    "  move {} from position {} to position {}",
+                    page_move.page,
+                    page_move.from_idx + 1,
+                    page_move.to_idx + 1
+                );
+            }
+
+            let fixed: Vec<String> = fixed.iter().map(|page| page.to_string()).collect();
+            println!("  fixed update: {}", fixed.join(","));
+        }
+        Err(_) => println!("  can't be fixed, its rules form a cycle"),
+    }
+}
+
+/// renders a DOT digraph, edges that some update breaks are drawn red
+fn render_dot(
+    nodes: &[(usize, String)],
+    edges: &[(usize, usize)],
+    violated: &HashSet<(usize, usize)>,
+) -> String {
+    let mut dot = String::from("digraph rules {\n");
+
+    for (page, label) in nodes {
+        dot.push_str(&format!("    {page} [label=\"{label}\"];\n"));
+    }
+
+    for (before, after) in edges {
+        let style = if violated.contains(&(*before, *after)) {
+            " [color=red, penwidth=2]"
+        } else {
+            ""
+        };
+        dot.push_str(&format!("    {before} -> {after}{style};\n"));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn get_sorted_edges(rules: &Rules, pages: Option<&[usize]>) -> Vec<(usize, usize)> {
+    let mut edges: Vec<(usize, usize)> = rules
+        .iter()
+        .flat_map(|(before, nums_after)| nums_after.iter().map(|after| (*before, *after)))
+        .filter(|(before, after)| {
+            pages.is_none_or(|pages| pages.contains(before) && pages.contains(after))
+        })
+        .collect();
+
+    edges.sort_unstable();
+    edges
+}
+
+fn render_rule_graph(rules: &Rules, updates: &[Vec<usize>]) -> String {
+    let edges = get_sorted_edges(rules, None);
+
+    let mut pages: Vec<usize> = edges
+        .iter()
+        .flat_map(|(before, after)| [*before, *after])
+        .collect();
+    pages.sort_unstable();
+    pages.dedup();
+
+    let nodes: Vec<(usize, String)> = pages.iter().map(|page| (*page, page.to_string())).collect();
+
+    let violated: HashSet<(usize, usize)> = updates
+        .iter()
+        .flat_map(|update| get_violations(update, rules))
+        .map(|violation| (violation.before, violation.after))
+        .collect();
+
+    render_dot(&nodes, &edges, &violated)
+}
+
+/// only the pages of the update and the rules between them, labeled with their positions
+fn render_update_graph(update: &[usize], rules: &Rules) -> String {
+    let edges = get_sorted_edges(rules, Some(update));
+
+    let nodes: Vec<(usize, String)> = update
+        .iter()
+        .enumerate()
+        .map(|(idx, page)| (*page, format!("{page}\\n#{}", idx + 1)))
+        .collect();
+
+    let violated: HashSet<(usize, usize)> = get_violations(update, rules)
+        .into_iter()
+        .map(|violation| (violation.before, violation.after))
+        .collect();
+
+    render_dot(&nodes, &edges, &violated)
+}
+
+/// an update with an even number of pages has no true middle
+fn get_middle_page(update: &[usize]) -> Option<usize> {
+    if update.len().is_multiple_of(2) {
+        return None;
+    }
+
+    Some(update[update.len() / 2])
+}
+
+fn print_table(rows: &[[String; 5]]) {
+    let header = ["#", "status", "original", "corrected", "middle"].map(|title| title.to_string());
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in [&header].into_iter().chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+
+        println!("{}", cells.join("  ").trim_end());
+    }
 }
 
 fn main() -> Result<(), Error> {
@@ -82,14 +502,283 @@ fn main() -> Result<(), Error> {
 
     let rules_map = get_rules(rules);
 
-    let sum: usize = updates
-        .iter()
-        .filter(|update| !is_valid_update(update, &rules_map))
-        .map(|update| fix_invalid_update(update, &rules_map))
-        .map(|update| update[update.len() / 2])
-        .sum();
+    if let Some(arg) = std::env::args().find(|arg| arg.starts_with("--dot")) {
+        let dot = match arg.strip_prefix("--dot=") {
+            Some(update_nr) => {
+                let update = update_nr
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|update_nr| updates.get(update_nr.checked_sub(1)?))
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("No update number {update_nr}"),
+                        )
+                    })?;
+
+                render_update_graph(update, &rules_map)
+            }
+            None => render_rule_graph(&rules_map, &updates),
+        };
+
+        print!("{dot}");
+        return Ok(());
+    }
+
+    let mut valid_sum = 0;
+    let mut fixed_sum = 0;
+    let mut rows: Vec<[String; 5]> = vec![];
+
+    for (update_idx, update) in updates.iter().enumerate() {
+        let is_valid = is_valid_update(update, &rules_map);
+
+        if !is_valid && std::env::args().any(|arg| arg == "--explain") {
+            print_explanation(update_idx, update, &rules_map);
+        }
+
+        let (status, corrected) = if is_valid {
+            valid_sum += get_middle_page(update).unwrap_or(0);
+            ("valid", Some(update.clone()))
+        } else {
+            match fix_invalid_update(update, &rules_map) {
+                Ok(fixed) => {
+                    fixed_sum += get_middle_page(&fixed).unwrap_or(0);
+                    ("fixed", Some(fixed))
+                }
+                Err(cycle) => {
+                    let pages: Vec<String> = cycle
+                        .pages
+                        .iter()
+                        .chain(cycle.pages.first())
+                        .map(|page| page.to_string())
+                        .collect();
+
+                    println!(
+                        "Update {} can't be fixed, its rules form a cycle: {}",
+                        update_idx + 1,
+                        pages.join(" -> ")
+                    );
+
+                    ("cycle", None)
+                }
+            }
+        };
+
+        if update.len().is_multiple_of(2) {
+            println!(
+                "Update {} has an even number of pages and no middle page, it is left out of the sums",
+                update_idx + 1
+            );
+        }
+
+        let format_pages = |pages: &[usize]| {
+            pages
+                .iter()
+                .map(|page| page.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
 
-    println!("Sum is {sum}");
+        rows.push([
+            (update_idx + 1).to_string(),
+            status.to_string(),
+            format_pages(update),
+            corrected.as_deref().map_or("-".to_string(), format_pages),
+            match corrected.as_deref().map(get_middle_page) {
+                Some(Some(middle)) => middle.to_string(),
+                Some(None) => "even length".to_string(),
+                None => "-".to_string(),
+            },
+        ]);
+    }
+
+    if std::env::args().any(|arg| arg == "--table") {
+        print_table(&rows);
+    }
+
+    println!("Valid sum is {valid_sum}");
+    println!("Fixed sum is {fixed_sum}");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE_RULES: &[&str] = &[
+        "47|53", "97|13", "97|61", "97|47", "75|29", "61|13", "75|53", "29|13", "97|29", "53|29",
+        "61|53", "97|53", "61|29", "47|13", "75|47", "97|75", "47|61", "75|61", "47|29", "75|13",
+        "53|13",
+    ];
+
+    static EXAMPLE_UPDATES: &[&[usize]] = &[
+        &[75, 47, 61, 53, 29],
+        &[97, 61, 53, 29, 13],
+        &[75, 29, 13],
+        &[75, 97, 47, 61, 53],
+        &[61, 13, 29],
+        &[97, 13, 75, 29, 47],
+    ];
+
+    fn rules(lines: &[&str]) -> Rules {
+        get_rules(
+            &lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// applies the moves one after another like `print_explanation` describes them
+    fn replay(update: &[usize], moves: &[Move]) -> Vec<usize> {
+        let mut current = update.to_vec();
+
+        for page_move in moves {
+            assert_ne!(page_move.from_idx, page_move.to_idx);
+            assert_eq!(current.remove(page_move.from_idx), page_move.page);
+            current.insert(page_move.to_idx, page_move.page);
+        }
+
+        current
+    }
+
+    fn longest_common_subsequence(left: &[usize], right: &[usize]) -> usize {
+        let mut lengths = vec![vec![0; right.len() + 1]; left.len() + 1];
+
+        for (left_idx, left_page) in left.iter().enumerate() {
+            for (right_idx, right_page) in right.iter().enumerate() {
+                lengths[left_idx + 1][right_idx + 1] = if left_page == right_page {
+                    lengths[left_idx][right_idx] + 1
+                } else {
+                    lengths[left_idx][right_idx + 1].max(lengths[left_idx + 1][right_idx])
+                };
+            }
+        }
+
+        lengths[left.len()][right.len()]
+    }
+
+    fn permutations(pages: &[usize]) -> Vec<Vec<usize>> {
+        if pages.is_empty() {
+            return vec![vec![]];
+        }
+
+        (0..pages.len())
+            .flat_map(|idx| {
+                let mut rest = pages.to_vec();
+                let page = rest.remove(idx);
+
+                permutations(&rest).into_iter().map(move |mut permutation| {
+                    permutation.insert(0, page);
+                    permutation
+                })
+            })
+            .collect()
+    }
+
+    /// every page outside the longest subsequence shared with some valid order has to move once
+    fn brute_force_move_count(update: &[usize], rules: &Rules) -> usize {
+        let kept = permutations(update)
+            .iter()
+            .filter(|permutation| is_valid_update(permutation, rules))
+            .map(|permutation| longest_common_subsequence(update, permutation))
+            .max()
+            .expect("rules without a cycle allow some order");
+
+        update.len() - kept
+    }
+
+    #[test]
+    fn example_sums() {
+        let rules = rules(EXAMPLE_RULES);
+
+        let valid_sum: usize = EXAMPLE_UPDATES
+            .iter()
+            .filter(|update| is_valid_update(update, &rules))
+            .filter_map(|update| get_middle_page(update))
+            .sum();
+        let fixed_sum: usize = EXAMPLE_UPDATES
+            .iter()
+            .filter(|update| !is_valid_update(update, &rules))
+            .map(|update| fix_invalid_update(update, &rules).ok().unwrap())
+            .filter_map(|fixed| get_middle_page(&fixed))
+            .sum();
+
+        assert_eq!(valid_sum, 143);
+        assert_eq!(fixed_sum, 123);
+    }
+
+    #[test]
+    fn example_update_6_needs_two_moves() {
+        let rules = rules(EXAMPLE_RULES);
+        let update = EXAMPLE_UPDATES[5];
+
+        let (fixed, moves) = get_minimal_moves(update, &rules).ok().unwrap();
+
+        assert_eq!(fixed, vec![97, 75, 47, 29, 13]);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(replay(update, &moves), fixed);
+    }
+
+    #[test]
+    fn minimal_moves_match_brute_force() {
+        let mut state: u64 = 0x2024_1205;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            (state % bound as u64) as usize
+        };
+
+        for _ in 0..150 {
+            // rules only point from lower to higher pages, so they never form a cycle
+            let rule_lines = (10..17)
+                .flat_map(|before| ((before + 1)..17).map(move |after| (before, after)))
+                .filter(|_| next(2) == 0)
+                .map(|(before, after)| format!("{before}|{after}"))
+                .collect::<Vec<_>>();
+            let rules = get_rules(&rule_lines);
+
+            let mut pages = (10..17).collect::<Vec<_>>();
+            let update = (0..2 + next(5))
+                .map(|_| pages.remove(next(pages.len())))
+                .collect::<Vec<_>>();
+
+            let (fixed, moves) = get_minimal_moves(&update, &rules).ok().unwrap();
+
+            assert!(is_valid_update(&fixed, &rules), "update {update:?}");
+            assert_eq!(replay(&update, &moves), fixed, "update {update:?}");
+            assert_eq!(
+                moves.len(),
+                brute_force_move_count(&update, &rules),
+                "update {update:?} with rules {rule_lines:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rule_cycle_is_reported() {
+        let rules = rules(&["11|22", "22|33", "33|11", "11|44"]);
+        let update = [44, 33, 22, 11];
+
+        let Err(cycle) = fix_invalid_update(&update, &rules) else {
+            panic!("update with a rule cycle got fixed");
+        };
+
+        let mut pages = cycle.pages.clone();
+        pages.sort_unstable();
+        assert_eq!(pages, vec![11, 22, 33]);
+
+        for (idx, page) in cycle.pages.iter().enumerate() {
+            let next_page = cycle.pages[(idx + 1) % cycle.pages.len()];
+            assert!(
+                rules[page].contains(&next_page),
+                "{page}|{next_page} is a rule"
+            );
+        }
+
+        assert!(get_minimal_moves(&update, &rules).is_err());
+    }
+}
@@ -2,7 +2,8 @@ use core::panic;
 use std::{
     collections::HashSet,
     fs::File,
-    io::{BufRead, BufReader, Error},
+    io::{BufRead, BufReader, Error, ErrorKind},
+    path::Path,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -23,6 +24,25 @@ impl Direction {
         }
     }
 
+    fn from_tile(tile: char) -> Option<Direction> {
+        match tile {
+            '^' => Some(Direction::Up),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            '>' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    fn to_idx(&self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        }
+    }
+
     fn turn_right(&self) -> Direction {
         match self {
             Direction::Up => Direction::Right,
@@ -42,15 +62,22 @@ fn walk_direction(current_pos: (usize, usize), direction: &Direction) -> Option<
     Some((new_y, new_x))
 }
 
+/// every step of the guard in order, turning on the spot counts as a step too
+type Route = Vec<(usize, usize, Direction)>;
+
+/// guards walk independently of each other, so the start tile of another guard is just floor
 fn get_path(
     starting_pos: (usize, usize),
+    starting_direction: &Direction,
     map: &[Vec<char>],
-) -> Option<HashSet<(usize, usize, Direction)>> {
+) -> Option<Route> {
     let mut visited_tiles: HashSet<(usize, usize, Direction)> = HashSet::new();
-    visited_tiles.insert((starting_pos.0, starting_pos.1, Direction::Up));
+    visited_tiles.insert((starting_pos.0, starting_pos.1, starting_direction.clone()));
+
+    let mut route: Route = vec![(starting_pos.0, starting_pos.1, starting_direction.clone())];
 
     let mut current_pos = starting_pos;
-    let mut current_direction = Direction::Up;
+    let mut current_direction = starting_direction.clone();
 
     while let Some(new_pos) = walk_direction(current_pos, &current_direction) {
         let next_tile = map.get(new_pos.0).and_then(|line| line.get(new_pos.1));
@@ -59,54 +86,290 @@ fn get_path(
             Some('#') => {
                 current_direction = current_direction.turn_right();
             }
-            Some('.' | '^') => {
+            Some('.' | '^' | 'v' | '<' | '>') => {
                 current_pos = new_pos;
-
-                if visited_tiles.contains(&(new_pos.0, new_pos.1, current_direction.clone())) {
-                    return None;
-                }
-
-                visited_tiles.insert((new_pos.0, new_pos.1, current_direction.clone()));
             }
             Some(tile) => {
                 panic!("Unexpected next tile {tile}")
             }
         }
+
+        let step = (current_pos.0, current_pos.1, current_direction.clone());
+
+        // a guard boxed in by obstacles keeps turning on the spot, which is a loop as well
+        if !visited_tiles.insert(step.clone()) {
+            return None;
+        }
+
+        route.push(step);
     }
 
-    Some(visited_tiles)
+    Some(route)
+}
+
+fn get_distinct_tile_count(routes: &[Route]) -> usize {
+    routes
+        .iter()
+        .flatten()
+        .map(|(y, x, _)| (*y, *x))
+        .collect::<HashSet<_>>()
+        .len()
 }
 
-fn get_obstacle_count(starting_pos: (usize, usize), map: &[Vec<char>]) -> usize {
-    let visited_tiles = get_path(starting_pos, map).expect("normal path cannot have loop");
+static ALL_DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
 
-    let mut obstacle_map: HashSet<(usize, usize)> = HashSet::new();
+/// the stop for each direction, indexed by `Direction::to_idx`
+type Stops = [Option<(usize, usize)>; 4];
 
-    visited_tiles
-        .iter()
-        .filter_map(|(y, x, _)| {
-            let mut new_map = map.to_owned();
+/// for every tile and direction, the tile the guard stops on in front of the next obstacle,
+/// or `None` if it walks off the map
+struct JumpTable {
+    stops: Vec<Vec<Stops>>,
+}
+
+impl JumpTable {
+    fn new(map: &[Vec<char>]) -> Self {
+        let mut stops = map
+            .iter()
+            .map(|line| vec![[None; 4]; line.len()])
+            .collect::<Vec<_>>();
 
-            if *y >= new_map.len() || *x >= new_map[*y].len() {
-                return None;
+        for direction in &ALL_DIRECTIONS {
+            // the tile in front has to be done first, so walk against the direction
+            let mut positions: Vec<(usize, usize)> = map
+                .iter()
+                .enumerate()
+                .flat_map(|(y, line)| (0..line.len()).map(move |x| (y, x)))
+                .collect();
+
+            if matches!(direction, Direction::Down | Direction::Right) {
+                positions.reverse();
             }
 
-            if new_map[*y][*x] == '#' {
-                return None;
+            for pos in positions {
+                let next_pos = walk_direction(pos, direction).filter(|next_pos| {
+                    map.get(next_pos.0)
+                        .is_some_and(|line| next_pos.1 < line.len())
+                });
+
+                stops[pos.0][pos.1][direction.to_idx()] = match next_pos {
+                    None => None,
+                    Some(next_pos) if map[next_pos.0][next_pos.1] == '#' => Some(pos),
+                    Some(next_pos) => stops[next_pos.0][next_pos.1][direction.to_idx()],
+                };
             }
+        }
 
-            new_map[*y][*x] = '#';
+        Self { stops }
+    }
+
+    /// where the guard stops when walking from `pos`, taking one extra obstacle into account.
+    /// the extra obstacle only matters if it lies on the way to the precomputed stop
+    fn jump(
+        &self,
+        pos: (usize, usize),
+        direction: &Direction,
+        obstacle: Option<(usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        let stop = self.stops[pos.0][pos.1][direction.to_idx()];
+
+        let Some(obstacle) = obstacle else {
+            return stop;
+        };
+
+        let (dy, dx) = direction.to_idx_diff();
+        let distance_to = |target: (usize, usize)| {
+            let ty = target.0 as isize - pos.0 as isize;
+            let tx = target.1 as isize - pos.1 as isize;
 
-            match get_path(starting_pos, &new_map) {
-                None => Some((*y, *x)),
-                Some(_) => None,
+            if ty * dx == tx * dy {
+                Some(ty * dy + tx * dx)
+            } else {
+                None
             }
+        };
+
+        let Some(obstacle_distance) = distance_to(obstacle).filter(|distance| *distance > 0) else {
+            return stop;
+        };
+
+        let blocks_earlier = match stop {
+            Some(stop) => {
+                distance_to(stop).is_some_and(|stop_distance| obstacle_distance <= stop_distance)
+            }
+            None => true,
+        };
+
+        if !blocks_earlier {
+            return stop;
+        }
+
+        Some((
+            pos.0.checked_add_signed((obstacle_distance - 1) * dy)?,
+            pos.1.checked_add_signed((obstacle_distance - 1) * dx)?,
+        ))
+    }
+}
+
+/// walks from obstacle to obstacle, the guard loops once it stops at the same tile facing the
+/// same way twice
+fn has_loop(
+    table: &JumpTable,
+    starting_pos: (usize, usize),
+    starting_direction: Direction,
+    obstacle: (usize, usize),
+) -> bool {
+    let mut stops: HashSet<(usize, usize, Direction)> = HashSet::new();
+
+    let mut current_pos = starting_pos;
+    let mut current_direction = starting_direction;
+
+    while let Some(stop) = table.jump(current_pos, &current_direction, Some(obstacle)) {
+        if !stops.insert((stop.0, stop.1, current_direction.clone())) {
+            return true;
+        }
+
+        current_pos = stop;
+        current_direction = current_direction.turn_right();
+    }
+
+    false
+}
+
+/// an obstacle can go on any tile the guard walks over, except where it starts
+fn get_obstacle_candidates(route: &Route, starting_pos: (usize, usize)) -> HashSet<(usize, usize)> {
+    route
+        .iter()
+        .map(|(y, x, _)| (*y, *x))
+        .filter(|pos| *pos != starting_pos)
+        .collect()
+}
+
+fn get_obstacle_count(
+    starting_pos: (usize, usize),
+    starting_direction: &Direction,
+    map: &[Vec<char>],
+) -> usize {
+    let route =
+        get_path(starting_pos, starting_direction, map).expect("normal path cannot have loop");
+    let table = JumpTable::new(map);
+
+    get_obstacle_candidates(&route, starting_pos)
+        .into_iter()
+        .filter(|obstacle_pos| {
+            has_loop(
+                &table,
+                starting_pos,
+                starting_direction.clone(),
+                *obstacle_pos,
+            )
         })
-        .for_each(|obstacle_pos| {
-            obstacle_map.insert(obstacle_pos);
-        });
+        .count()
+}
+
+/// renders the map with every tile any guard visits marked as `X`
+fn render_route(map: &[Vec<char>], routes: &[Route]) -> String {
+    let mut map = map.to_owned();
+
+    for (y, x, _) in routes.iter().flatten() {
+        map[*y][*x] = 'X';
+    }
 
-    obstacle_map.len()
+    map.iter()
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// renders the route like the puzzle does, `|` and `-` for walking up/down and left/right,
+/// `+` where a guard does both, and the guards' starts left as they are
+fn render_route_lines(map: &[Vec<char>], routes: &[Route]) -> String {
+    let mut map = map.to_owned();
+
+    for (y, x, direction) in routes.iter().flatten() {
+        let tile = &mut map[*y][*x];
+
+        let is_vertical = matches!(direction, Direction::Up | Direction::Down);
+
+        *tile = match (*tile, is_vertical) {
+            (guard @ ('^' | 'v' | '<' | '>'), _) => guard,
+            ('|', false) | ('-', true) | ('+', _) => '+',
+            (_, true) => '|',
+            (_, false) => '-',
+        };
+    }
+
+    map.iter()
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_route_steps(route: &Route) -> String {
+    route
+        .iter()
+        .enumerate()
+        .map(|(step_idx, (y, x, direction))| format!("{step_idx}: {y},{x} {direction:?}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn get_guards(map: &[Vec<char>]) -> Vec<((usize, usize), Direction)> {
+    map.iter()
+        .enumerate()
+        .flat_map(|(line_idx, line)| {
+            line.iter().enumerate().filter_map(move |(col_idx, tile)| {
+                Direction::from_tile(*tile).map(|direction| ((line_idx, col_idx), direction))
+            })
+        })
+        .collect()
+}
+
+/// flags unknown tiles, a map without guards, and guards that loop before any obstacle is added
+fn validate(map: &[Vec<char>]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (line_idx, line) in map.iter().enumerate() {
+        for (col_idx, tile) in line.iter().enumerate() {
+            if *tile == '.' || *tile == '#' || Direction::from_tile(*tile).is_some() {
+                continue;
+            }
+
+            problems.push(format!(
+                "Unknown tile '{tile}' at line {}, column {}",
+                line_idx + 1,
+                col_idx + 1
+            ));
+        }
+    }
+
+    let guards = get_guards(map);
+
+    if guards.is_empty() {
+        problems.push("Expected at least one guard '^', '>', 'v' or '<', found none".to_string());
+    }
+
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    for (guard_idx, (pos, direction)) in guards.iter().enumerate() {
+        if get_path(*pos, direction, map).is_none() {
+            problems.push(format!(
+                "Guard {} at line {}, column {} is stuck in a loop without any added obstacle",
+                guard_idx + 1,
+                pos.0 + 1,
+                pos.1 + 1
+            ));
+        }
+    }
+
+    problems
 }
 
 fn main() -> Result<(), Error> {
@@ -119,20 +382,154 @@ fn main() -> Result<(), Error> {
         .map(|line| line.chars().collect())
         .collect();
 
-    let starting_pos = map
+    let problems = validate(&map);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+
+        return Err(Error::new(ErrorKind::InvalidData, "map is unsolvable"));
+    }
+
+    let guards = get_guards(&map);
+    let routes: Vec<Route> = guards
         .iter()
-        .enumerate()
-        .find_map(|(line_idx, line)| {
-            line.iter()
-                .enumerate()
-                .find(|(_, c)| **c == '^')
-                .map(|(col_idx, _)| (line_idx, col_idx))
+        .map(|(pos, direction)| {
+            get_path(*pos, direction, &map).expect("normal path cannot have loop")
         })
-        .expect("starting position");
+        .collect();
+
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    if let [_, command, rest @ ..] = args.as_slice() {
+        if command == "route" {
+            match rest.first().map(|format| format.as_str()) {
+                None | Some("steps") => {
+                    for (guard_idx, route) in routes.iter().enumerate() {
+                        if routes.len() > 1 {
+                            println!("Guard {}:", guard_idx + 1);
+                        }
+
+                        println!("{}", format_route_steps(route));
+                    }
+                }
+                Some("map") => println!("{}", render_route_lines(&map, &routes)),
+                Some(format) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown route format {format}"),
+                    ))
+                }
+            }
 
-    let obstacle_count = get_obstacle_count(starting_pos, &map);
+            return Ok(());
+        }
+    }
 
-    println!("Obstacle count is {obstacle_count}");
+    if let [_, command, name, ..] = args.as_slice() {
+        if command == "snapshot" {
+            let update = std::env::args().any(|arg| arg == "--update");
+            return snapshot::check(
+                Path::new("snapshots"),
+                name,
+                &render_route(&map, &routes),
+                update,
+            );
+        }
+    }
+
+    for (guard_idx, ((pos, direction), route)) in guards.iter().zip(&routes).enumerate() {
+        if guards.len() > 1 {
+            println!(
+                "Guard {} at {},{} facing {direction:?}:",
+                guard_idx + 1,
+                pos.0,
+                pos.1
+            );
+        }
+
+        println!(
+            "Distinct tiles visited: {}",
+            get_distinct_tile_count(std::slice::from_ref(route))
+        );
+
+        let obstacle_count = get_obstacle_count(*pos, direction, &map);
+
+        println!("Obstacle count is {obstacle_count}");
+    }
+
+    if guards.len() > 1 {
+        println!(
+            "Tiles visited by any guard: {}",
+            get_distinct_tile_count(&routes)
+        );
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE_MAP: &[&str] = &[
+        "....#.....",
+        ".........#",
+        "..........",
+        "..#.......",
+        ".......#..",
+        "..........",
+        ".#..^.....",
+        "........#.",
+        "#.........",
+        "......#...",
+    ];
+
+    fn example_map() -> Vec<Vec<char>> {
+        EXAMPLE_MAP
+            .iter()
+            .map(|line| line.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn example_gives_known_answers() {
+        let map = example_map();
+        assert!(validate(&map).is_empty());
+
+        let (pos, direction) = get_guards(&map).remove(0);
+        let route = get_path(pos, &direction, &map).unwrap();
+
+        assert_eq!(get_distinct_tile_count(std::slice::from_ref(&route)), 41);
+        assert_eq!(get_obstacle_count(pos, &direction, &map), 6);
+    }
+
+    #[test]
+    fn start_tile_is_never_an_obstacle_candidate() {
+        let map = example_map();
+
+        let (pos, direction) = get_guards(&map).remove(0);
+        let route = get_path(pos, &direction, &map).unwrap();
+
+        // the example route crosses its start tile again later on
+        let start_visits = route.iter().filter(|(y, x, _)| (*y, *x) == pos).count();
+        assert!(start_visits > 1);
+
+        let candidates = get_obstacle_candidates(&route, pos);
+        assert!(!candidates.contains(&pos));
+    }
+
+    #[test]
+    fn example_route_matches_snapshot() {
+        let map = example_map();
+
+        let routes: Vec<Route> = get_guards(&map)
+            .iter()
+            .map(|(pos, direction)| get_path(*pos, direction, &map).unwrap())
+            .collect();
+
+        snapshot::assert_snapshot!("example_route", &render_route(&map, &routes));
+    }
+}
@@ -3,6 +3,8 @@ use std::{
     io::{BufRead, BufReader, Error},
 };
 
+use stats::Stats;
+
 fn concatenate_numbers(left: usize, right: usize) -> usize {
     let left_str = left.to_string();
     let right_str = right.to_string();
@@ -10,7 +12,9 @@ fn concatenate_numbers(left: usize, right: usize) -> usize {
     concatenated.parse().unwrap()
 }
 
-fn is_valid_equation(target: usize, current: usize, numbers: &[usize]) -> bool {
+fn is_valid_equation(target: usize, current: usize, numbers: &[usize], stats: &mut Stats) -> bool {
+    stats.incr("Recursive calls");
+
     if current > target {
         return false;
     }
@@ -19,21 +23,22 @@ fn is_valid_equation(target: usize, current: usize, numbers: &[usize]) -> bool {
         return current == target;
     };
 
-    is_valid_equation(target, current + next_num, numbers_left)
-        || is_valid_equation(target, current * next_num, numbers_left)
+    is_valid_equation(target, current + next_num, numbers_left, stats)
+        || is_valid_equation(target, current * next_num, numbers_left, stats)
         || is_valid_equation(
             target,
             concatenate_numbers(current, *next_num),
             numbers_left,
+            stats,
         )
 }
 
-fn sum_valid_equations(equations: &[(usize, Vec<usize>)]) -> usize {
+fn sum_valid_equations(equations: &[(usize, Vec<usize>)], stats: &mut Stats) -> usize {
     equations
         .iter()
         .filter(|(target, numbers)| {
             let (first_num, numbers_left) = numbers.split_first().expect("more than one number");
-            is_valid_equation(*target, *first_num, numbers_left)
+            is_valid_equation(*target, *first_num, numbers_left, stats)
         })
         .map(|(target, _)| target)
         .sum()
@@ -60,9 +65,12 @@ fn main() -> Result<(), Error> {
         })
         .collect();
 
-    let sum = sum_valid_equations(&equations);
+    let mut stats = Stats::new(&["Recursive calls"]);
+    let sum = sum_valid_equations(&equations, &mut stats);
 
     println!("Sum is {sum}");
 
+    stats.print_if_requested();
+
     Ok(())
 }
@@ -0,0 +1,136 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Error, ErrorKind},
+};
+
+/// builds a solver config from its defaults, the `key = value` lines of `config.txt` and then
+/// `--key=value` arguments, which win over the file. `set` applies one entry to the config
+pub fn load<T: Default>(set: fn(&mut T, &str, &str) -> Result<(), String>) -> Result<T, Error> {
+    let mut config = T::default();
+
+    if let Ok(file) = File::open("config.txt") {
+        apply_lines(&mut config, BufReader::new(file), set)?;
+    }
+
+    apply_args(&mut config, std::env::args().skip(1), set)?;
+
+    Ok(config)
+}
+
+/// skips blank lines and `#` comments, errors name the line they come from
+fn apply_lines<T>(
+    config: &mut T,
+    reader: impl BufRead,
+    set: fn(&mut T, &str, &str) -> Result<(), String>,
+) -> Result<(), Error> {
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid_line = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("config.txt line {}: {err}", line_idx + 1),
+            )
+        };
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid_line(format!("expected key = value, found '{line}'")))?;
+        set(config, key.trim(), value.trim()).map_err(invalid_line)?;
+    }
+
+    Ok(())
+}
+
+/// only looks at `--key=value` arguments, plain flags and commands are left to the solver
+fn apply_args<T>(
+    config: &mut T,
+    args: impl Iterator<Item = String>,
+    set: fn(&mut T, &str, &str) -> Result<(), String>,
+) -> Result<(), Error> {
+    for arg in args {
+        if let Some((key, value)) = arg.strip_prefix("--").and_then(|arg| arg.split_once('=')) {
+            set(config, key, value).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Config {
+        width: usize,
+        strict: bool,
+    }
+
+    fn set(config: &mut Config, key: &str, value: &str) -> Result<(), String> {
+        let invalid = |_| format!("Invalid value {value} for {key}");
+
+        match key {
+            "width" => config.width = value.parse().map_err(invalid)?,
+            "strict" => {
+                config.strict = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value {value} for {key}"))?
+            }
+            key => return Err(format!("Unknown config key {key}")),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_win_over_file() {
+        let mut config = Config::default();
+
+        apply_lines(
+            &mut config,
+            "# room\n\nwidth = 11\nstrict=true\n".as_bytes(),
+            set,
+        )
+        .unwrap();
+        apply_args(
+            &mut config,
+            ["repl", "--stats", "--width=7"]
+                .map(String::from)
+                .into_iter(),
+            set,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                width: 7,
+                strict: true
+            }
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let mut config = Config::default();
+
+        let err = apply_lines(&mut config, "width = 11\nheight\n".as_bytes(), set).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "config.txt line 2: expected key = value, found 'height'"
+        );
+
+        let err = apply_lines(&mut config, "\nwidth = x\n".as_bytes(), set).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.txt line 2: Invalid value x for width"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+/// compares a rendered state with its fixture `<dir>/<name>.txt`
+///
+/// the fixture is only written when `update` is set, a missing fixture is reported as an error
+pub fn check(dir: &Path, name: &str, rendered: &str, update: bool) -> Result<(), Error> {
+    let path = dir.join(format!("{name}.txt"));
+
+    if update {
+        fs::create_dir_all(dir)?;
+        fs::write(&path, format!("{}\n", rendered.trim_end()))?;
+        println!("Stored snapshot {}", path.display());
+        return Ok(());
+    }
+
+    if !path.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "snapshot {name} has no fixture at {}, rerun with --update to store it",
+                path.display()
+            ),
+        ));
+    }
+
+    let expected = fs::read_to_string(&path)?;
+
+    if expected.trim_end() == rendered.trim_end() {
+        println!("Snapshot {name} matches");
+        return Ok(());
+    }
+
+    println!("Snapshot {name} changed:");
+    println!("{}", render_diff(&expected, rendered));
+
+    Err(Error::other(format!("snapshot {name} does not match")))
+}
+
+/// checks a rendered state from a test against `snapshots/<name>.txt` in the calling crate,
+/// running the tests with `UPDATE_SNAPSHOTS=1` stores the fixtures instead
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($name:expr, $rendered:expr) => {
+        $crate::assert_fixture(
+            &::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots"),
+            $name,
+            $rendered,
+        )
+    };
+}
+
+#[doc(hidden)]
+pub fn assert_fixture(dir: &Path, name: &str, rendered: &str) {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    match check(dir, name, rendered, update) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            panic!("snapshot {name} has no fixture in {}, run the tests with UPDATE_SNAPSHOTS=1 to store it", dir.display())
+        }
+        Err(err) => panic!("{err}"),
+    }
+}
+
+/// lists both versions of every changed line with `^` under the differing characters
+fn render_diff(expected: &str, actual: &str) -> String {
+    let expected_lines = expected.trim_end().lines().collect::<Vec<_>>();
+    let actual_lines = actual.trim_end().lines().collect::<Vec<_>>();
+
+    let mut diff = Vec::new();
+
+    for line_idx in 0..expected_lines.len().max(actual_lines.len()) {
+        let expected_line = expected_lines.get(line_idx).copied().unwrap_or_default();
+        let actual_line = actual_lines.get(line_idx).copied().unwrap_or_default();
+
+        if expected_line == actual_line {
+            diff.push(format!("  {actual_line}"));
+            continue;
+        }
+
+        let expected_chars = expected_line.chars().collect::<Vec<_>>();
+        let actual_chars = actual_line.chars().collect::<Vec<_>>();
+
+        let markers = (0..expected_chars.len().max(actual_chars.len()))
+            .map(|idx| {
+                if expected_chars.get(idx) == actual_chars.get(idx) {
+                    ' '
+                } else {
+                    '^'
+                }
+            })
+            .collect::<String>();
+
+        diff.push(format!("- {expected_line}"));
+        diff.push(format!("+ {actual_line}"));
+        diff.push(format!("  {}", markers.trim_end()));
+    }
+
+    diff.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("snapshot-{}-{test_name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn missing_fixture_fails_without_update() {
+        let dir = fixture_dir("missing");
+
+        let err = check(&dir, "grid", "#.\n.#", false).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(!dir.join("grid.txt").exists());
+    }
+
+    #[test]
+    fn update_stores_fixture_that_matches_afterwards() {
+        let dir = fixture_dir("update");
+
+        check(&dir, "grid", "#.\n.#", true).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("grid.txt")).unwrap(),
+            "#.\n.#\n"
+        );
+        assert!(check(&dir, "grid", "#.\n.#", false).is_ok());
+        assert!(check(&dir, "grid", "#.\n##", false).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diff_marks_changed_characters() {
+        let diff = render_diff("#.\n.#\n", "#.\n##");
+
+        assert_eq!(diff, "  #.\n- .#\n+ ##\n  ^");
+    }
+}
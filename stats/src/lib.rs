@@ -0,0 +1,75 @@
+/// named counters a solver bumps while it works, to compare algorithms by the work they do
+#[derive(Debug, Default)]
+pub struct Stats {
+    counters: Vec<(&'static str, usize)>,
+}
+
+impl Stats {
+    /// starts every counter at zero, so counters that are never bumped still get printed
+    pub fn new(names: &[&'static str]) -> Self {
+        Self {
+            counters: names.iter().map(|name| (*name, 0)).collect(),
+        }
+    }
+
+    pub fn add(&mut self, name: &'static str, amount: usize) {
+        match self
+            .counters
+            .iter_mut()
+            .find(|(counter_name, _)| *counter_name == name)
+        {
+            Some((_, count)) => *count += amount,
+            None => self.counters.push((name, amount)),
+        }
+    }
+
+    pub fn incr(&mut self, name: &'static str) {
+        self.add(name, 1);
+    }
+
+    pub fn get(&self, name: &str) -> usize {
+        self.counters
+            .iter()
+            .find(|(counter_name, _)| *counter_name == name)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// prints every counter in the order they were declared or first bumped
+    pub fn print(&self) {
+        for (name, count) in &self.counters {
+            println!("{name}: {count}");
+        }
+    }
+
+    /// prints the counters if the solver was started with `--stats`
+    pub fn print_if_requested(&self) {
+        if std::env::args().any(|arg| arg == "--stats") {
+            self.print();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_counters_start_at_zero() {
+        let stats = Stats::new(&["Cache hits", "Cache misses"]);
+
+        assert_eq!(stats.get("Cache hits"), 0);
+        assert_eq!(stats.counters, vec![("Cache hits", 0), ("Cache misses", 0)]);
+    }
+
+    #[test]
+    fn counters_keep_their_order() {
+        let mut stats = Stats::new(&["Pushed"]);
+
+        stats.incr("Popped");
+        stats.add("Pushed", 3);
+        stats.incr("Popped");
+
+        assert_eq!(stats.counters, vec![("Pushed", 3), ("Popped", 2)]);
+        assert_eq!(stats.get("Unknown"), 0);
+    }
+}